@@ -0,0 +1,604 @@
+// bhb：无界面的命令行工具，只依赖 history-core，便于在服务器 / cron 中生成报表
+use clap::{Parser, Subcommand, ValueEnum};
+use comfy_table::{presets::UTF8_FULL_CONDENSED, Table};
+use history_core::api::{self, HistoryFilters};
+use history_core::config::AppConfig;
+use history_core::domain::{AppError, AppResult, HistoryItem, SiteVisits};
+use history_core::launcher::{self, LauncherIndex};
+use history_core::report::{self, ReportFormat};
+use history_core::{db, server, vault};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "bhb", version, about = "浏览器历史记录命令行工具")]
+struct Cli {
+    /// 数据库路径，默认使用应用配置中的数据库
+    #[arg(long, global = true)]
+    db: Option<String>,
+    /// 以 JSON 格式输出
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 分页列出历史记录
+    List {
+        #[arg(short, long)]
+        keyword: Option<String>,
+        /// 7d / 30d / 90d / all / 起始时间戳-结束时间戳
+        #[arg(short, long)]
+        range: Option<String>,
+        /// title / last_visited_time / num_visits / frecency
+        #[arg(long)]
+        sort: Option<String>,
+        /// asc / desc
+        #[arg(long)]
+        order: Option<String>,
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+        /// 按规范化 URL 归并重复页面
+        #[arg(long)]
+        collapse: bool,
+    },
+    /// 概览统计
+    Stats {
+        #[arg(short, long)]
+        range: Option<String>,
+    },
+    /// 按类别统计访问次数（分类规则见配置 category_rules）
+    Categories {
+        #[arg(short, long)]
+        range: Option<String>,
+    },
+    /// 按站点的每日访问趋势：7 / 28 天滑动平均与 z-score 异常日
+    Trends {
+        #[arg(short, long, default_value = "90d")]
+        range: String,
+        /// 只看指定站点
+        #[arg(long)]
+        site: Option<String>,
+        #[arg(long, default_value_t = 10)]
+        top: u32,
+        /// z-score 异常阈值
+        #[arg(long)]
+        threshold: Option<f64>,
+    },
+    /// 搜索建议
+    Search {
+        query: String,
+        #[arg(long, default_value_t = 10)]
+        limit: u32,
+    },
+    /// 将浏览器历史数据库复制到应用目录并设为当前数据库
+    Import { path: String },
+    /// 处理应用目录中导入留下的明文快照：encrypt 用当前密码加密，wipe 擦除
+    Snapshots {
+        #[arg(value_enum)]
+        action: SnapshotAction,
+    },
+    /// 导出全部匹配的历史记录
+    Export {
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        keyword: Option<String>,
+        #[arg(short, long)]
+        range: Option<String>,
+        /// 输出文件，省略时写到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 列出首次访问落在时间范围内的站点与页面
+    Discover {
+        #[arg(short, long, default_value = "7d")]
+        range: String,
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// 对比两个时间范围：站点 / 类别访问次数变化、新出现与消失的站点、名次变化
+    Compare {
+        /// 基准时间范围
+        range_a: String,
+        /// 对比时间范围
+        range_b: String,
+        #[arg(long, default_value_t = 20)]
+        top: u32,
+    },
+    /// 生成周报 / 月报摘要
+    Report {
+        /// 7d / 30d / 起始时间戳-结束时间戳，与上一个等长时间范围对比
+        #[arg(short, long, default_value = "7d")]
+        range: String,
+        #[arg(short, long, value_enum, default_value_t = ReportKind::Markdown)]
+        format: ReportKind,
+        /// 输出文件，省略时写到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// 启动器模式：逐行读取查询，输出按相关度排序的 `标题\tURL`，每个查询以空行结束
+    Launcher {
+        #[arg(long, default_value_t = launcher::DEFAULT_LIMIT)]
+        limit: usize,
+        /// 改为监听 Unix socket（仅 Unix）
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// 在 127.0.0.1 上启动 HTTP/JSON 接口，前台运行直到进程退出
+    Serve {
+        #[arg(long, default_value_t = server::DEFAULT_PORT)]
+        port: u16,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SnapshotAction {
+    Encrypt,
+    Wipe,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportKind {
+    Markdown,
+    Html,
+}
+
+const EXPORT_PAGE_SIZE: u32 = 500;
+
+fn internal<E: std::fmt::Display>(e: E) -> AppError {
+    AppError::Internal(e.to_string())
+}
+
+// 打开数据库；加密数据库从环境变量 BHB_PASSPHRASE 读取密码解锁
+fn open_database(db_path: Option<&str>) -> AppResult<()> {
+    let path = match db_path {
+        Some(p) => {
+            if !Path::new(p).is_file() {
+                return Err(AppError::Invalid(format!("数据库文件不存在: {}", p)));
+            }
+            db::reset_connection(p)?;
+            p.to_string()
+        }
+        None => match AppConfig::load().map_err(internal)?.get_db_path() {
+            Some(p) => p,
+            None => return Ok(()),
+        },
+    };
+    if !vault::is_encrypted_file(Path::new(&path)) || vault::is_unlocked() {
+        return Ok(());
+    }
+
+    let passphrase = std::env::var("BHB_PASSPHRASE").map_err(|_| {
+        AppError::Invalid("数据库已加密，请通过环境变量 BHB_PASSPHRASE 提供密码".into())
+    })?;
+    let data = std::fs::read(&path).map_err(internal)?;
+    let (key, plaintext) =
+        vault::decrypt(&passphrase, &data).map_err(|e| AppError::Invalid(e.to_string()))?;
+    vault::start_session(key);
+    db::open_decrypted(&plaintext)?;
+    Ok(())
+}
+
+fn format_time(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let mut out: String = text.chars().take(max_chars - 1).collect();
+        out.push('…');
+        out
+    }
+}
+
+fn new_table(header: &[&str]) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_header(header.to_vec());
+    table
+}
+
+// 下游管道提前关闭（如 | head）时静默结束
+fn stdout_result(result: std::io::Result<()>) -> AppResult<()> {
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(internal(e)),
+        _ => Ok(()),
+    }
+}
+
+fn emit(text: impl std::fmt::Display) -> AppResult<()> {
+    stdout_result(writeln!(std::io::stdout().lock(), "{}", text))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> AppResult<()> {
+    emit(serde_json::to_string_pretty(value).map_err(internal)?)
+}
+
+fn history_filters(
+    keyword: Option<String>,
+    range: Option<String>,
+    sort: Option<String>,
+    order: Option<String>,
+    collapse: bool,
+) -> HistoryFilters {
+    HistoryFilters {
+        keyword,
+        time_range: range,
+        locale: None,
+        sort_by: sort,
+        sort_order: order,
+        transition: None,
+        collapse_duplicates: Some(collapse),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_export(
+    out: &mut dyn Write,
+    items: &[HistoryItem],
+    format: ExportFormat,
+) -> std::io::Result<()> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, items)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Csv => {
+            writeln!(out, "url,title,last_visited_time,num_visits")?;
+            for item in items {
+                writeln!(
+                    out,
+                    "{},{},{},{}",
+                    csv_field(&item.url),
+                    csv_field(item.title.as_deref().unwrap_or("")),
+                    item.last_visited_time,
+                    item.num_visits
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> AppResult<()> {
+    // 导入不使用 --db，但启用加密时需要先解锁以便加密导入的副本
+    if matches!(cli.command, Command::Import { .. }) {
+        open_database(None)?;
+    } else {
+        open_database(cli.db.as_deref())?;
+    }
+
+    match cli.command {
+        Command::List {
+            keyword,
+            range,
+            sort,
+            order,
+            page,
+            limit,
+            collapse,
+        } => {
+            let filters = history_filters(keyword, range, sort, order, collapse);
+            let resp = api::list_history(page, limit, filters)?;
+            if cli.json {
+                return print_json(&resp);
+            }
+            let mut table = new_table(&["最后访问", "次数", "标题", "URL"]);
+            for item in &resp.items {
+                table.add_row(vec![
+                    format_time(item.last_visited_time),
+                    item.num_visits.to_string(),
+                    truncate(item.title.as_deref().unwrap_or(""), 40),
+                    truncate(&item.url, 80),
+                ]);
+            }
+            emit(table)?;
+            emit(format!("共 {} 条，第 {} 页", resp.total, page))?;
+        }
+        Command::Stats { range } => {
+            let stats = api::stats_overview(range)?;
+            if cli.json {
+                return print_json(&stats);
+            }
+            let mut table = new_table(&["指标", "值"]);
+            table.add_row(vec![
+                "总访问次数".to_string(),
+                stats.total_visits.to_string(),
+            ]);
+            table.add_row(vec!["站点数".to_string(), stats.distinct_sites.to_string()]);
+            table.add_row(vec!["Top 站点".to_string(), stats.top_entities.join("\n")]);
+            let queries: Vec<String> = stats
+                .top_queries
+                .iter()
+                .map(|q| format!("{} ({}, {})", q.query, q.engine, q.count))
+                .collect();
+            table.add_row(vec!["热门搜索".to_string(), queries.join("\n")]);
+            emit(table)?;
+        }
+        Command::Categories { range } => {
+            let items = api::category_distribution(range)?;
+            if cli.json {
+                return print_json(&items);
+            }
+            let mut table = new_table(&["类别", "访问次数"]);
+            for item in &items {
+                table.add_row(vec![item.category.clone(), item.visits.to_string()]);
+            }
+            emit(table)?;
+        }
+        Command::Trends {
+            range,
+            site,
+            top,
+            threshold,
+        } => {
+            let report = api::site_trends(Some(range), site, Some(top), threshold)?;
+            if cli.json {
+                return print_json(&report);
+            }
+            let mut table = new_table(&["站点", "访问次数", "7日均值", "28日均值", "异常天数"]);
+            for trend in &report.sites {
+                let last = trend.points.last();
+                table.add_row(vec![
+                    trend.site.clone(),
+                    trend.total_visits.to_string(),
+                    last.map(|p| format!("{:.1}", p.avg_7d)).unwrap_or_default(),
+                    last.map(|p| format!("{:.1}", p.avg_28d))
+                        .unwrap_or_default(),
+                    trend.anomaly_days.to_string(),
+                ]);
+            }
+            emit(table)?;
+            if report.sites.iter().all(|t| t.anomaly_days == 0) {
+                return Ok(());
+            }
+            let mut anomalies = new_table(&["日期", "站点", "访问次数", "28日均值", "z-score"]);
+            for trend in &report.sites {
+                for p in trend.points.iter().filter(|p| p.anomaly) {
+                    anomalies.add_row(vec![
+                        p.day.clone(),
+                        trend.site.clone(),
+                        p.visits.to_string(),
+                        format!("{:.1}", p.avg_28d),
+                        p.z_score.map(|z| format!("{:.1}", z)).unwrap_or_default(),
+                    ]);
+                }
+            }
+            emit(anomalies)?;
+        }
+        Command::Search { query, limit } => {
+            let suggestions = api::search_suggest(query, Some(limit))?;
+            if cli.json {
+                return print_json(&suggestions);
+            }
+            let mut table = new_table(&["类型", "内容", "URL", "得分"]);
+            for s in &suggestions {
+                table.add_row(vec![
+                    serde_json::to_value(s.kind)
+                        .ok()
+                        .and_then(|v| v.as_str().map(str::to_string))
+                        .unwrap_or_default(),
+                    truncate(&s.text, 40),
+                    truncate(s.url.as_deref().unwrap_or(""), 80),
+                    format!("{:.2}", s.score),
+                ]);
+            }
+            emit(table)?;
+        }
+        Command::Import { path } => {
+            let copied = api::copy_browser_db_to_app(path)?;
+            api::set_db_path(copied.clone())?;
+            if cli.json {
+                return print_json(&serde_json::json!({ "db_path": copied }));
+            }
+            emit(format!("已导入并切换到: {}", copied))?;
+        }
+        Command::Snapshots { action } => {
+            let message = match action {
+                SnapshotAction::Encrypt => api::encrypt_snapshots()?,
+                SnapshotAction::Wipe => api::wipe_snapshots()?,
+            };
+            emit(message)?;
+        }
+        Command::Export {
+            format,
+            keyword,
+            range,
+            output,
+        } => {
+            let mut items = Vec::new();
+            let mut page = 1;
+            loop {
+                let filters = history_filters(keyword.clone(), range.clone(), None, None, false);
+                let resp = api::list_history(page, EXPORT_PAGE_SIZE, filters)?;
+                let done = resp.items.len() < EXPORT_PAGE_SIZE as usize;
+                items.extend(resp.items);
+                if done {
+                    break;
+                }
+                page += 1;
+            }
+
+            match output {
+                Some(path) => {
+                    let mut file = std::fs::File::create(&path).map_err(internal)?;
+                    write_export(&mut file, &items, format).map_err(internal)?;
+                    eprintln!("已导出 {} 条记录到 {}", items.len(), path.display());
+                }
+                None => stdout_result(write_export(&mut std::io::stdout().lock(), &items, format))?,
+            }
+        }
+        Command::Discover { range, limit } => {
+            let resp = api::list_first_seen(Some(range), Some(limit))?;
+            if cli.json {
+                return print_json(&resp);
+            }
+            let mut table = new_table(&["首次访问", "访问次数", "站点"]);
+            for site in &resp.sites {
+                table.add_row(vec![
+                    format_time(site.first_seen),
+                    site.visits.to_string(),
+                    site.site.clone(),
+                ]);
+            }
+            emit(table)?;
+            let mut table = new_table(&["首次访问", "访问次数", "标题", "URL"]);
+            for page in &resp.pages {
+                table.add_row(vec![
+                    format_time(page.first_seen),
+                    page.visits.to_string(),
+                    truncate(page.title.as_deref().unwrap_or(""), 40),
+                    truncate(&page.url, 80),
+                ]);
+            }
+            emit(table)?;
+            emit(format!(
+                "新站点 {} 个，新页面 {} 个（参考历史快照 {} 个）",
+                resp.total_sites, resp.total_pages, resp.snapshots_used
+            ))?;
+        }
+        Command::Compare {
+            range_a,
+            range_b,
+            top,
+        } => {
+            let cmp = api::compare_periods(range_a, range_b, Some(top))?;
+            if cli.json {
+                return print_json(&cmp);
+            }
+            for (label, changes) in [("站点", &cmp.sites), ("类别", &cmp.categories)] {
+                let mut table = new_table(&[label, "A", "B", "变化", "名次"]);
+                for c in changes {
+                    table.add_row(vec![
+                        c.key.clone(),
+                        c.visits_a.to_string(),
+                        c.visits_b.to_string(),
+                        match c.change_pct {
+                            Some(pct) => format!("{:+} ({:+.1}%)", c.change, pct),
+                            None => format!("{:+}", c.change),
+                        },
+                        match (c.rank_a, c.rank_b) {
+                            (Some(a), Some(b)) => format!("{} → {}", a, b),
+                            (None, Some(b)) => format!("新 → {}", b),
+                            (Some(a), None) => format!("{} → 无", a),
+                            (None, None) => String::new(),
+                        },
+                    ]);
+                }
+                emit(table)?;
+            }
+            let names = |items: &[SiteVisits]| {
+                items
+                    .iter()
+                    .map(|s| format!("{} ({})", s.site, s.visits))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            emit(format!("总访问: {} → {}", cmp.total_a, cmp.total_b))?;
+            emit(format!("新出现: {}", names(&cmp.appeared)))?;
+            emit(format!("已消失: {}", names(&cmp.disappeared)))?;
+        }
+        Command::Report {
+            range,
+            format,
+            output,
+        } => {
+            let digest = api::digest_report(Some(range))?;
+            if cli.json {
+                return print_json(&digest);
+            }
+            let content = report::render(
+                &digest,
+                match format {
+                    ReportKind::Markdown => ReportFormat::Markdown,
+                    ReportKind::Html => ReportFormat::Html,
+                },
+            );
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content).map_err(internal)?;
+                    eprintln!("报告已写入 {}", path.display());
+                }
+                None => emit(content)?,
+            }
+        }
+        Command::Launcher { limit, socket } => {
+            let index = LauncherIndex::build()?;
+            match socket {
+                #[cfg(unix)]
+                Some(path) => {
+                    eprintln!("已载入 {} 条记录，监听 {}", index.len(), path.display());
+                    std::sync::Arc::new(index)
+                        .serve_socket(&path, limit)
+                        .map_err(internal)?;
+                }
+                #[cfg(not(unix))]
+                Some(_) => return Err(AppError::Invalid("当前平台不支持 Unix socket".into())),
+                None => stdout_result(index.serve_lines(
+                    std::io::stdin().lock(),
+                    std::io::stdout().lock(),
+                    limit,
+                ))?,
+            }
+        }
+        Command::Serve { port } => {
+            let status = server::start(port)?;
+            if cli.json {
+                print_json(&status)?;
+            } else {
+                emit(format!(
+                    "HTTP 服务已启动: http://127.0.0.1:{}/api/ ，令牌文件: {}",
+                    status.port,
+                    status.token_path.unwrap_or_default()
+                ))?;
+            }
+            // Ctrl-C / SIGTERM 时停止服务并删除令牌文件
+            let (tx, rx) = std::sync::mpsc::channel();
+            ctrlc::set_handler(move || {
+                let _ = tx.send(());
+            })
+            .map_err(internal)?;
+            let _ = rx.recv();
+            server::stop();
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("错误: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
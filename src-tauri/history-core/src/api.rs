@@ -0,0 +1,1351 @@
+use crate::canonical::CanonicalRules;
+use crate::categories::CategoryRules;
+use crate::config::AppConfig;
+use crate::db::with_conn;
+use crate::domain::{
+    ApiServerStatus, AppError, AppResult, CategoryVisits, DeletionReport, DigestReport,
+    DiscoveryResponse, DownloadItem, DownloadListResponse, DownloadState, HistoryDetail,
+    HistoryItem, HistoryListResponse, LockStatus, NavigationGraph, OverviewStats, PeriodComparison,
+    SearchQueryListResponse, SearchTermListResponse, SessionListResponse, SiteVisits, Suggestion,
+    TimeBudgetReport, TransitionStats, TrendReport,
+};
+use crate::exclusion::Blocklist;
+use crate::redact::{self, Redact, RedactionConfig};
+use crate::search_queries::SearchEngineRule;
+use crate::transition::Transition;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct HistoryFilters {
+    pub keyword: Option<String>,
+    pub time_range: Option<String>, // 7d / 30d / 90d / all
+    pub locale: Option<String>,
+    pub sort_by: Option<String>, // title, last_visited_time, num_visits, frecency
+    pub sort_order: Option<String>, // asc, desc
+    pub transition: Option<String>, // link, typed, reload, redirect ...
+    pub collapse_duplicates: Option<bool>, // 按规范化 URL 归并重复页面
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct GraphOptions {
+    pub time_range: Option<String>,
+    pub level: Option<String>, // page / site
+    pub min_weight: Option<i64>,
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SearchTermFilters {
+    pub keyword: Option<String>,
+    pub time_range: Option<String>,
+    pub sort_by: Option<String>, // count / recent
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DownloadFilters {
+    pub keyword: Option<String>,
+    pub time_range: Option<String>,
+    pub state: Option<String>, // in_progress / complete / cancelled / interrupted
+    pub mime_type: Option<String>, // 前缀匹配，如 image/
+    pub sort_by: Option<String>, // start_time, total_bytes, file_name
+    pub sort_order: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeleteFilters {
+    pub keyword: Option<String>,
+    pub domain: Option<String>,
+    pub time_range: Option<String>,
+}
+
+fn compute_time_lower(bound: &Option<String>) -> Option<i64> {
+    let now = chrono::Utc::now().timestamp();
+    match bound.as_deref() {
+        Some("7d") => Some(now - 7 * 86400),
+        Some("30d") => Some(now - 30 * 86400),
+        Some("90d") => Some(now - 90 * 86400),
+        Some("all") | None => None,
+        Some(range) => {
+            // 处理自定义范围格式: "startTs-endTs"
+            if let Some(dash_pos) = range.find('-') {
+                if let Ok(start_ts) = range[..dash_pos].parse::<i64>() {
+                    return Some(start_ts);
+                }
+            }
+            None
+        }
+    }
+}
+
+fn compute_time_upper(bound: &Option<String>) -> Option<i64> {
+    match bound.as_deref() {
+        Some(range) => {
+            // 处理自定义范围格式: "startTs-endTs"
+            if let Some(dash_pos) = range.find('-') {
+                if let Ok(end_ts) = range[dash_pos + 1..].parse::<i64>() {
+                    return Some(end_ts);
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn build_order_clause(
+    sort_by: &Option<String>,
+    sort_order: &Option<String>,
+    half_life_days: f64,
+) -> String {
+    let order = match sort_order.as_deref() {
+        Some("asc") => "ASC",
+        Some("desc") | None => "DESC", // 默认降序
+        _ => "DESC",
+    };
+
+    let sort_field = match sort_by.as_deref() {
+        Some("title") => "title",
+        Some("num_visits") => "num_visits",
+        // 相关度：访问次数 × 时间衰减，由注册的 SQL 函数计算
+        Some("frecency") | Some("relevance") => {
+            let now = chrono::Utc::now().timestamp();
+            return format!(
+                "ORDER BY frecency(num_visits, last_visited_time, {}, {}) {}, last_visited_time DESC, url",
+                now, half_life_days, order
+            );
+        }
+        Some("last_visited_time") | None => "last_visited_time", // 默认按访问时间排序
+        _ => "last_visited_time",
+    };
+
+    // 以 url 作最后的排序键，保证排序唯一，分页（含导出逐页读取）不会重复或遗漏同值记录
+    format!("ORDER BY {} {}, url", sort_field, order)
+}
+
+pub fn list_history(
+    page: u32,
+    page_size: u32,
+    filters: HistoryFilters,
+) -> AppResult<HistoryListResponse> {
+    if page_size == 0 || page_size > 500 {
+        return Err(AppError::Invalid("page_size out of range".into()));
+    }
+    let offset = (page.saturating_sub(1) * page_size) as i64;
+
+    // 排除列表命中的记录一律不返回
+    let mut where_clauses: Vec<String> = vec![crate::exclusion::condition("url", "title")];
+    let mut params_dyn: Vec<rusqlite::types::Value> = Vec::new();
+    let mut param_index = 1;
+
+    if let Some(ts_lower) = compute_time_lower(&filters.time_range) {
+        where_clauses.push(format!("last_visited_time >= ?{}", param_index));
+        params_dyn.push(ts_lower.into());
+        param_index += 1;
+    }
+    if let Some(ts_upper) = compute_time_upper(&filters.time_range) {
+        where_clauses.push(format!("last_visited_time <= ?{}", param_index));
+        params_dyn.push(ts_upper.into());
+        param_index += 1;
+    }
+    if let Some(locale) = &filters.locale {
+        if !locale.is_empty() {
+            where_clauses.push(format!("locale = ?{}", param_index));
+            params_dyn.push(locale.clone().into());
+            param_index += 1;
+        }
+    }
+    if let Some(transition) = &filters.transition {
+        if !transition.is_empty() {
+            let transition = Transition::parse(transition)
+                .ok_or_else(|| AppError::Invalid(format!("未知的跳转类型: {}", transition)))?;
+            let kind = with_conn(crate::source::detect)?;
+            let sub_sql = crate::visits::urls_with_transition_sql(kind, transition)
+                .ok_or_else(|| AppError::Invalid("当前数据源不包含跳转类型信息".into()))?;
+            where_clauses.push(format!("url IN ({})", sub_sql));
+        }
+    }
+    if let Some(kw) = &filters.keyword {
+        if !kw.is_empty() {
+            where_clauses.push(format!(
+                "(title LIKE ?{} OR url LIKE ?{})",
+                param_index,
+                param_index + 1
+            ));
+            params_dyn.push(format!("%{}%", kw).into());
+            params_dyn.push(format!("%{}%", kw).into());
+            param_index += 2;
+        }
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        "".into()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
+    let config = AppConfig::load().unwrap_or_default();
+    let order_clause = build_order_clause(
+        &filters.sort_by,
+        &filters.sort_order,
+        config.frecency_half_life_days,
+    );
+    // 归并模式：按 canonical_url 分组，访问次数求和、取最近访问时间，
+    // url/title 取最近访问的那一条（SQLite 单一 MAX 聚合下的裸列语义）
+    let source_sql = if filters.collapse_duplicates.unwrap_or(false) {
+        format!(
+            "(SELECT url, title, MAX(last_visited_time) AS last_visited_time,
+                SUM(num_visits) AS num_visits, COUNT(*) AS duplicate_count
+            FROM navigation_history {} GROUP BY canonical_url(url))",
+            where_sql
+        )
+    } else {
+        format!(
+            "(SELECT url, title, last_visited_time, num_visits, 1 AS duplicate_count
+            FROM navigation_history {})",
+            where_sql
+        )
+    };
+    let sql_items = format!(
+        "SELECT url, title, last_visited_time, num_visits, duplicate_count FROM {} {} LIMIT ?{} OFFSET ?{}",
+        source_sql, order_clause, param_index, param_index + 1
+    );
+    let sql_count = format!("SELECT COUNT(*) FROM {}", source_sql);
+
+    let items = with_conn(|conn| {
+        let mut stmt = conn.prepare(&sql_items)?;
+
+        // 构建完整的参数向量：dynamic params + limit + offset
+        let mut all_params: Vec<rusqlite::types::Value> = params_dyn.clone();
+        all_params.push((page_size as i64).into());
+        all_params.push(offset.into());
+
+        let mut rows = stmt.query(rusqlite::params_from_iter(all_params.iter()))?;
+        let mut acc = Vec::new();
+        while let Some(row) = rows.next()? {
+            acc.push(HistoryItem {
+                url: row.get(0)?,
+                title: row.get(1)?,
+                last_visited_time: row.get(2)?,
+                num_visits: row.get(3)?,
+                duplicate_count: row.get(4)?,
+            });
+        }
+        Ok::<_, rusqlite::Error>(acc)
+    })?;
+
+    let total: i64 = with_conn(|conn| {
+        let mut stmt = conn.prepare(&sql_count)?;
+        let val: i64 =
+            stmt.query_row(rusqlite::params_from_iter(params_dyn.iter()), |r| r.get(0))?;
+        Ok(val)
+    })?;
+
+    Ok(redact::apply(HistoryListResponse { items, total }))
+}
+
+pub fn get_history_detail(url: String) -> AppResult<HistoryDetail> {
+    if url.is_empty() {
+        return Err(AppError::Invalid("url 不能为空".into()));
+    }
+    let redactor = redact::current();
+    let mut detail = with_conn(|conn| {
+        if let Some(detail) = crate::detail::load_detail(conn, &url)? {
+            return Ok(Some(detail));
+        }
+        // 列表中返回的是脱敏后的 URL，需先还原
+        match redactor
+            .as_ref()
+            .map(|r| redact::resolve_url(conn, r, &url))
+            .transpose()?
+            .flatten()
+        {
+            Some(original) => crate::detail::load_detail(conn, &original),
+            None => Ok(None),
+        }
+    })?
+    .ok_or_else(|| AppError::Invalid(format!("未找到历史记录: {}", url)))?;
+
+    if let Some(r) = &redactor {
+        detail.redact(r);
+    }
+    Ok(detail)
+}
+
+pub fn search_suggest(q: String, limit: Option<u32>) -> AppResult<Vec<Suggestion>> {
+    let limit = limit.unwrap_or(10);
+    if limit == 0 || limit > 50 {
+        return Err(AppError::Invalid("limit out of range".into()));
+    }
+    if q.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    // 确保连接（及索引）已初始化
+    with_conn(|_| Ok(()))?;
+    let config = AppConfig::load().unwrap_or_default();
+    crate::suggest::query(&q, limit as usize, config.frecency_half_life_days)
+        .map(redact::apply)
+        .ok_or_else(|| AppError::Internal("搜索建议索引不可用".into()))
+}
+
+pub fn list_sessions(
+    time_range: Option<String>,
+    gap_minutes: Option<u32>,
+    page: u32,
+    page_size: u32,
+) -> AppResult<SessionListResponse> {
+    if page_size == 0 || page_size > 500 {
+        return Err(AppError::Invalid("page_size out of range".into()));
+    }
+    let config = AppConfig::load().unwrap_or_default();
+    let gap_minutes = gap_minutes.unwrap_or(config.session_gap_minutes);
+    if gap_minutes == 0 || gap_minutes > 1440 {
+        return Err(AppError::Invalid("gap_minutes out of range".into()));
+    }
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+
+    let visits = with_conn(|conn| {
+        let kind = crate::source::detect(conn)?;
+        crate::visits::load_visits(conn, kind, ts_lower, ts_upper)
+    })?;
+
+    // 最新的会话在前
+    let mut sessions = crate::sessions::sessionize(visits, gap_minutes as i64 * 60);
+    sessions.reverse();
+    let total = sessions.len() as i64;
+    let offset = (page.saturating_sub(1) * page_size) as usize;
+    let sessions = sessions
+        .into_iter()
+        .skip(offset)
+        .take(page_size as usize)
+        .collect();
+
+    Ok(redact::apply(SessionListResponse {
+        sessions,
+        total,
+        gap_minutes,
+    }))
+}
+
+pub fn time_budget(
+    time_range: Option<String>,
+    granularity: Option<String>,
+    top_n: Option<u32>,
+) -> AppResult<TimeBudgetReport> {
+    use crate::timespent::{self, Granularity};
+
+    let granularity = Granularity::parse(granularity.as_deref())
+        .ok_or_else(|| AppError::Invalid("granularity 仅支持 day / week".into()))?;
+    let top_n = top_n.unwrap_or(10);
+    if top_n == 0 || top_n > 100 {
+        return Err(AppError::Invalid("top_n out of range".into()));
+    }
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+
+    // 停留时长以会话间隔为空闲阈值，在汇总表中按日预先估算
+    let (sites, categories) = with_conn(|conn| {
+        crate::rollup::refresh(conn)?;
+        crate::rollup::daily_tallies(conn, ts_lower, ts_upper)
+    })?;
+
+    Ok(timespent::build_report(
+        &sites,
+        &categories,
+        granularity,
+        top_n as usize,
+    ))
+}
+
+fn load_navigation_graph(options: &GraphOptions) -> AppResult<NavigationGraph> {
+    use crate::navgraph::{self, GraphLevel};
+
+    let level = GraphLevel::parse(options.level.as_deref())
+        .ok_or_else(|| AppError::Invalid("level 仅支持 page / site".into()))?;
+    let max_nodes = options.max_nodes.unwrap_or(200);
+    if max_nodes == 0 || max_nodes > 5000 {
+        return Err(AppError::Invalid("max_nodes out of range".into()));
+    }
+    let min_weight = options.min_weight.unwrap_or(1).max(1);
+    let ts_lower = compute_time_lower(&options.time_range);
+    let ts_upper = compute_time_upper(&options.time_range);
+
+    let (visits, referrer_urls) = with_conn(|conn| {
+        let kind = crate::source::detect(conn)?;
+        if !kind.has_visits() {
+            return Ok(None);
+        }
+        let visits = crate::visits::load_visits(conn, kind, ts_lower, ts_upper)?;
+        // 来源访问可能早于时间范围下限，单独补查
+        let loaded: std::collections::HashSet<i64> = visits.iter().map(|v| v.visit_id).collect();
+        let mut missing: Vec<i64> = visits
+            .iter()
+            .filter_map(|v| v.from_visit)
+            .filter(|id| !loaded.contains(id))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        let referrer_urls = crate::visits::load_visit_urls(conn, kind, &missing)?;
+        Ok(Some((visits, referrer_urls)))
+    })?
+    .ok_or_else(|| AppError::Invalid("当前数据源不包含页面跳转信息".into()))?;
+
+    let graph = navgraph::build_graph(
+        &visits,
+        &referrer_urls,
+        level,
+        min_weight,
+        max_nodes as usize,
+    );
+    // 展示与导出共用，统一在此脱敏
+    Ok(redact::apply(graph))
+}
+
+pub fn navigation_graph(options: GraphOptions) -> AppResult<NavigationGraph> {
+    load_navigation_graph(&options)
+}
+
+pub fn export_navigation_graph(options: GraphOptions, format: String) -> AppResult<String> {
+    let graph = load_navigation_graph(&options)?;
+    let (content, ext) = match format.as_str() {
+        "graphml" => (crate::navgraph::to_graphml(&graph), "graphml"),
+        "dot" => (crate::navgraph::to_dot(&graph), "dot"),
+        _ => return Err(AppError::Invalid("format 仅支持 graphml / dot".into())),
+    };
+
+    let export_dir = AppConfig::get_export_dir().map_err(|e| AppError::Internal(e.to_string()))?;
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let target_path = export_dir.join(format!("navigation_graph_{}.{}", timestamp, ext));
+    std::fs::write(&target_path, content)
+        .map_err(|e| AppError::Internal(format!("写入导出文件失败: {}", e)))?;
+
+    Ok(target_path.to_string_lossy().to_string())
+}
+
+pub fn transition_stats(
+    time_range: Option<String>,
+    top_n: Option<u32>,
+) -> AppResult<TransitionStats> {
+    let top_n = top_n.unwrap_or(10);
+    if top_n == 0 || top_n > 100 {
+        return Err(AppError::Invalid("top_n out of range".into()));
+    }
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+
+    let visits = with_conn(|conn| {
+        let kind = crate::source::detect(conn)?;
+        if !kind.has_visits() {
+            return Ok(None);
+        }
+        crate::visits::load_visits(conn, kind, ts_lower, ts_upper).map(Some)
+    })?
+    .ok_or_else(|| AppError::Invalid("当前数据源不包含跳转类型信息".into()))?;
+
+    Ok(crate::transition::build_stats(&visits, top_n as usize))
+}
+
+pub fn list_search_queries(
+    page: u32,
+    page_size: u32,
+    time_range: Option<String>,
+    engine: Option<String>,
+) -> AppResult<SearchQueryListResponse> {
+    if page_size == 0 || page_size > 500 {
+        return Err(AppError::Invalid("page_size out of range".into()));
+    }
+    let config = AppConfig::load().unwrap_or_default();
+    let rules = crate::search_queries::effective_rules(&config.search_engine_rules);
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+    let engine = engine.filter(|e| !e.is_empty());
+
+    let items = with_conn(|conn| {
+        crate::search_queries::collect_queries(conn, &rules, ts_lower, ts_upper, engine.as_deref())
+    })?;
+
+    let total = items.len() as i64;
+    let offset = (page.saturating_sub(1) * page_size) as usize;
+    let items = items
+        .into_iter()
+        .skip(offset)
+        .take(page_size as usize)
+        .collect();
+    Ok(SearchQueryListResponse { items, total })
+}
+
+pub fn list_search_terms(
+    page: u32,
+    page_size: u32,
+    filters: SearchTermFilters,
+) -> AppResult<SearchTermListResponse> {
+    if page_size == 0 || page_size > 500 {
+        return Err(AppError::Invalid("page_size out of range".into()));
+    }
+    let offset = (page.saturating_sub(1) * page_size) as i64;
+    let ts_lower = compute_time_lower(&filters.time_range);
+    let ts_upper = compute_time_upper(&filters.time_range);
+    let order_by_recent = matches!(filters.sort_by.as_deref(), Some("recent"));
+
+    let result = with_conn(|conn| {
+        if crate::source::detect(conn)? != crate::source::SourceKind::Chromium
+            || !crate::source::table_exists(conn, "keyword_search_terms")?
+        {
+            return Ok(None);
+        }
+        crate::search_terms::list_search_terms(
+            conn,
+            filters.keyword.as_deref(),
+            ts_lower,
+            ts_upper,
+            order_by_recent,
+            page_size as i64,
+            offset,
+        )
+        .map(Some)
+    })?;
+
+    let (items, total) =
+        result.ok_or_else(|| AppError::Invalid("仅 Chromium 数据源包含地址栏搜索词".into()))?;
+    Ok(redact::apply(SearchTermListResponse { items, total }))
+}
+
+pub fn list_downloads(
+    page: u32,
+    page_size: u32,
+    filters: DownloadFilters,
+) -> AppResult<DownloadListResponse> {
+    if page_size == 0 || page_size > 500 {
+        return Err(AppError::Invalid("page_size out of range".into()));
+    }
+    let state = match filters.state.as_deref() {
+        None | Some("") => None,
+        Some(s) => Some(
+            serde_json::from_value::<DownloadState>(serde_json::Value::String(s.to_string()))
+                .map_err(|_| AppError::Invalid(format!("未知的下载状态: {}", s)))?,
+        ),
+    };
+    let ts_lower = compute_time_lower(&filters.time_range);
+    let ts_upper = compute_time_upper(&filters.time_range);
+
+    let downloads = with_conn(|conn| {
+        let kind = crate::source::detect(conn)?;
+        if !kind.has_visits() {
+            return Ok(None);
+        }
+        crate::downloads::load_downloads(conn, kind).map(Some)
+    })?
+    .ok_or_else(|| AppError::Invalid("当前数据源不包含下载记录".into()))?;
+
+    let keyword = filters
+        .keyword
+        .as_deref()
+        .filter(|k| !k.is_empty())
+        .map(|k| k.to_lowercase());
+    let mime = filters.mime_type.as_deref().filter(|m| !m.is_empty());
+    let blocklist = AppConfig::load().unwrap_or_default().blocklist.normalized();
+    let mut items: Vec<DownloadItem> = downloads
+        .into_iter()
+        .filter(|d| ts_lower.is_none_or(|l| d.start_time >= l))
+        .filter(|d| ts_upper.is_none_or(|u| d.start_time <= u))
+        .filter(|d| {
+            !d.url_chain
+                .iter()
+                .chain(d.referrer.iter())
+                .chain(d.tab_url.iter())
+                .any(|u| blocklist.matches(u, None))
+        })
+        .filter(|d| state.is_none_or(|s| d.state == s))
+        .filter(|d| mime.is_none_or(|m| d.mime_type.as_deref().is_some_and(|t| t.starts_with(m))))
+        .filter(|d| {
+            keyword.as_deref().is_none_or(|kw| {
+                d.target_path.to_lowercase().contains(kw)
+                    || d.url_chain.iter().any(|u| u.to_lowercase().contains(kw))
+            })
+        })
+        .collect();
+
+    match filters.sort_by.as_deref() {
+        Some("total_bytes") => items.sort_by_key(|d| d.total_bytes.unwrap_or(d.received_bytes)),
+        Some("file_name") => items.sort_by(|a, b| a.file_name.cmp(&b.file_name)),
+        _ => items.sort_by_key(|d| d.start_time),
+    }
+    if filters.sort_order.as_deref() != Some("asc") {
+        items.reverse();
+    }
+
+    let total = items.len() as i64;
+    let offset = (page.saturating_sub(1) * page_size) as usize;
+    let items = items
+        .into_iter()
+        .skip(offset)
+        .take(page_size as usize)
+        .collect();
+    Ok(redact::apply(DownloadListResponse { items, total }))
+}
+
+pub fn delete_history(filters: DeleteFilters, dry_run: bool) -> AppResult<DeletionReport> {
+    let criteria = crate::deletion::DeleteCriteria {
+        keyword: filters.keyword.as_deref().filter(|k| !k.is_empty()),
+        domain: filters.domain.as_deref().filter(|d| !d.is_empty()),
+        lower: compute_time_lower(&filters.time_range),
+        upper: compute_time_upper(&filters.time_range),
+    };
+    if criteria.is_empty() {
+        return Err(AppError::Invalid("请至少指定一个删除条件".into()));
+    }
+
+    let report = with_conn(|conn| {
+        // 只允许修改应用目录内的副本，不触碰浏览器原始数据库
+        if !crate::db::is_app_owned(conn) {
+            return Ok(None);
+        }
+
+        let kind = crate::source::detect(conn)?;
+        let plan = crate::deletion::plan(conn, kind, &criteria)?;
+        let report = DeletionReport {
+            dry_run,
+            pages: plan.page_count(),
+            visits: plan.visit_count,
+        };
+        if !dry_run && (report.pages > 0 || report.visits > 0) {
+            crate::deletion::execute(conn, &plan)?;
+            crate::suggest::rebuild(conn);
+        }
+        Ok(Some(report))
+    })?
+    .ok_or_else(|| {
+        AppError::Invalid("只能删除应用目录中的数据库副本，请先复制到应用目录".into())
+    })?;
+
+    // 加密数据库在内存中修改，需重新加密写回
+    if let Some(key) = crate::vault::current_key().filter(|_| !report.dry_run) {
+        let plaintext = crate::db::serialize_current()?;
+        crate::vault::persist(&key, &plaintext).map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    Ok(report)
+}
+
+pub fn stats_overview(time_range: Option<String>) -> AppResult<OverviewStats> {
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+
+    // 整日部分读按日汇总表，首尾不满一天的部分直接查询数据源；排除的站点不计入
+    let (total_visits, site_counts) = with_conn(|conn| {
+        crate::rollup::refresh(conn)?;
+        crate::rollup::site_totals(conn, ts_lower, ts_upper)
+    })?;
+
+    let config = crate::config::AppConfig::load().unwrap_or_default();
+    let top_sites_limit = config.top_sites_count;
+    let distinct_sites = site_counts.len() as i64;
+    let mut top_sites: Vec<SiteVisits> = site_counts
+        .into_iter()
+        .map(|(site, visits)| SiteVisits { site, visits })
+        .collect();
+    top_sites.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.site.cmp(&b.site)));
+    top_sites.truncate(top_sites_limit as usize);
+
+    let rules = crate::search_queries::effective_rules(&config.search_engine_rules);
+    let mut top_queries = with_conn(|conn| {
+        crate::search_queries::collect_queries(conn, &rules, ts_lower, ts_upper, None)
+    })?;
+    top_queries.truncate(top_sites_limit as usize);
+
+    Ok(OverviewStats {
+        total_visits,
+        distinct_sites,
+        top_entities: top_sites.iter().map(|s| s.site.clone()).collect(),
+        top_sites,
+        top_queries,
+    })
+}
+
+// 按类别统计访问次数：自有格式的产品实体类别优先，其余按分类规则，未命中归为未分类
+pub fn category_distribution(time_range: Option<String>) -> AppResult<Vec<CategoryVisits>> {
+    let ts_lower = compute_time_lower(&time_range);
+    let ts_upper = compute_time_upper(&time_range);
+    let counts = with_conn(|conn| {
+        crate::rollup::refresh(conn)?;
+        crate::rollup::category_totals(conn, ts_lower, ts_upper)
+    })?;
+    let mut items: Vec<CategoryVisits> = counts
+        .into_iter()
+        .map(|(category, visits)| CategoryVisits { category, visits })
+        .collect();
+    items.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    Ok(items)
+}
+
+// 按站点的每日访问趋势：滑动平均与 z-score 异常标记在 Rust 中基于按日聚合的查询结果计算
+pub fn site_trends(
+    time_range: Option<String>,
+    site: Option<String>,
+    top_n: Option<u32>,
+    threshold: Option<f64>,
+) -> AppResult<TrendReport> {
+    use crate::localtime::local_day;
+    use crate::trends::{self, DEFAULT_Z_THRESHOLD, LONG_WINDOW_DAYS};
+
+    let threshold = threshold.unwrap_or(DEFAULT_Z_THRESHOLD);
+    if !threshold.is_finite() || threshold <= 0.0 {
+        return Err(AppError::Invalid("异常阈值必须大于0".into()));
+    }
+    let lower = compute_time_lower(&time_range);
+    let upper = compute_time_upper(&time_range);
+    let end = upper
+        .map(local_day)
+        .unwrap_or_else(|| chrono::Local::now().date_naive());
+    let lower_day = lower.map(local_day);
+    // 多取 28 天作为滑动平均与 z-score 的基线
+    let series_from = lower_day.map(|d| d - chrono::Duration::days(LONG_WINDOW_DAYS as i64));
+    let counts = with_conn(|conn| {
+        crate::rollup::refresh(conn)?;
+        crate::rollup::daily_site_counts(conn, series_from, end)
+    })?;
+
+    let earliest = counts.values().flat_map(|days| days.keys()).min().copied();
+    let start = lower_day.or(earliest).unwrap_or(end);
+    let series_start = series_from.unwrap_or(start).min(start);
+
+    let site = site
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty());
+    let mut totals: Vec<(&String, i64)> = counts
+        .iter()
+        .filter(|(name, _)| site.as_deref().is_none_or(|s| name.to_lowercase() == s))
+        .map(|(name, days)| {
+            let total = days
+                .iter()
+                .filter(|(day, _)| (start..=end).contains(*day))
+                .map(|(_, v)| v)
+                .sum();
+            (name, total)
+        })
+        .filter(|(_, total)| *total > 0)
+        .collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    totals.truncate(top_n.unwrap_or(10).clamp(1, 200) as usize);
+
+    Ok(TrendReport {
+        start_day: start.format("%Y-%m-%d").to_string(),
+        end_day: end.format("%Y-%m-%d").to_string(),
+        threshold,
+        sites: totals
+            .into_iter()
+            .map(|(name, _)| {
+                trends::build_series(name, &counts[name], series_start, start, end, threshold)
+            })
+            .collect(),
+    })
+}
+
+pub fn digest_report(time_range: Option<String>) -> AppResult<DigestReport> {
+    let now = chrono::Utc::now().timestamp();
+    let period_start = compute_time_lower(&time_range)
+        .ok_or_else(|| AppError::Invalid("报告需要有起点的时间范围，如 7d / 30d".into()))?;
+    let period_end = compute_time_upper(&time_range).unwrap_or(now);
+    if period_end <= period_start {
+        return Err(AppError::Invalid("时间范围无效".into()));
+    }
+    // 上一个等长时间范围
+    let previous_end = period_start - 1;
+    let previous_start = previous_end - (period_end - period_start);
+
+    let current = stats_overview(Some(format!("{}-{}", period_start, period_end)))?;
+    let previous = stats_overview(Some(format!("{}-{}", previous_start, previous_end)))?;
+    let config = AppConfig::load().unwrap_or_default();
+    let limit = config.top_sites_count as usize;
+
+    let (categories, hours, pages) = with_conn(|conn| {
+        let (lower, upper) = (Some(period_start), Some(period_end));
+        crate::rollup::refresh(conn)?;
+        let categories = crate::rollup::category_totals(conn, lower, upper)?;
+        let hours = crate::rollup::hourly_totals(conn, lower, upper)?;
+        let kind = crate::source::detect(conn)?;
+        let pages = crate::discovery::load_pages(conn, kind, Some(period_end))?;
+        Ok((categories, hours, pages))
+    })?;
+
+    let earliest = snapshot_first_seen(&config)?;
+    let mut new_sites = crate::discovery::new_sites(&pages, &earliest, period_start, period_end);
+    new_sites.truncate(limit);
+
+    Ok(DigestReport {
+        period_start,
+        period_end,
+        previous_start,
+        previous_end,
+        generated_at: now,
+        current,
+        previous,
+        new_sites,
+        top_categories: crate::report::top_categories(&categories, limit),
+        hourly_visits: crate::report::hourly_visits(&hours),
+    })
+}
+
+pub fn compare_periods(
+    range_a: String,
+    range_b: String,
+    top_n: Option<u32>,
+) -> AppResult<PeriodComparison> {
+    use crate::compare;
+
+    let top_n = top_n.unwrap_or(20);
+    if top_n == 0 || top_n > 500 {
+        return Err(AppError::Invalid("top_n out of range".into()));
+    }
+    let now = chrono::Utc::now().timestamp();
+    // 与报告相同：两个范围都需要有起点，且终点晚于起点
+    let period = |range: String| -> AppResult<(i64, i64)> {
+        let range = Some(range);
+        let start = compute_time_lower(&range)
+            .ok_or_else(|| AppError::Invalid("对比需要有起点的时间范围，如 7d / 30d".into()))?;
+        let end = compute_time_upper(&range).unwrap_or(now);
+        if end <= start {
+            return Err(AppError::Invalid("时间范围无效".into()));
+        }
+        Ok((start, end))
+    };
+    let (a_start, a_end) = period(range_a)?;
+    let (b_start, b_end) = period(range_b)?;
+
+    let ((total_a, sites_a), (total_b, sites_b), categories_a, categories_b) = with_conn(|conn| {
+        crate::rollup::refresh(conn)?;
+        Ok((
+            crate::rollup::site_totals(conn, Some(a_start), Some(a_end))?,
+            crate::rollup::site_totals(conn, Some(b_start), Some(b_end))?,
+            crate::rollup::category_totals(conn, Some(a_start), Some(a_end))?,
+            crate::rollup::category_totals(conn, Some(b_start), Some(b_end))?,
+        ))
+    })?;
+
+    Ok(PeriodComparison {
+        a_start: Some(a_start),
+        a_end,
+        b_start: Some(b_start),
+        b_end,
+        total_a,
+        total_b,
+        sites: compare::changes(&sites_a, &sites_b, top_n as usize),
+        categories: compare::changes(&categories_a, &categories_b, top_n as usize),
+        appeared: compare::only_in(&sites_b, &sites_a, top_n as usize),
+        disappeared: compare::only_in(&sites_a, &sites_b, top_n as usize),
+    })
+}
+
+// 应用目录中旧快照里的页面 / 站点最早访问时间
+fn snapshot_first_seen(config: &AppConfig) -> AppResult<crate::discovery::SnapshotFirstSeen> {
+    use crate::discovery;
+
+    let app_dir = AppConfig::get_app_dir().map_err(|e| AppError::Internal(e.to_string()))?;
+    let db_path = config.get_db_path();
+    let mut snapshots = discovery::snapshot_paths(&app_dir, db_path.as_deref());
+    let encrypted = discovery::encrypted_snapshot_paths(&app_dir, db_path.as_deref());
+    // 锁定时跳过加密快照会把老站点误报为新站点
+    let key = crate::vault::current_key();
+    if !encrypted.is_empty() && key.is_none() {
+        return Err(AppError::Invalid(format!(
+            "有 {} 个旧快照已加密，请先解锁",
+            encrypted.len()
+        )));
+    }
+    snapshots.extend(encrypted);
+    Ok(discovery::earliest_in_snapshots(
+        &snapshots,
+        key.as_ref(),
+        &config.blocklist,
+    ))
+}
+
+// 当前数据库的首次访问时间，再用应用目录中的旧快照往前修正
+fn load_first_seen_pages(
+    config: &AppConfig,
+) -> AppResult<(
+    Vec<crate::discovery::PageFirstSeen>,
+    crate::discovery::SnapshotFirstSeen,
+)> {
+    use crate::discovery;
+
+    let mut pages = with_conn(|conn| {
+        let kind = crate::source::detect(conn)?;
+        discovery::load_pages(conn, kind, None)
+    })?;
+    let earliest = snapshot_first_seen(config)?;
+    discovery::apply_snapshots(&mut pages, &earliest);
+    Ok((pages, earliest))
+}
+
+pub fn list_first_seen(
+    time_range: Option<String>,
+    limit: Option<u32>,
+) -> AppResult<DiscoveryResponse> {
+    let limit = limit.unwrap_or(50);
+    if limit == 0 || limit > 1000 {
+        return Err(AppError::Invalid("limit out of range".into()));
+    }
+    let lower = compute_time_lower(&time_range)
+        .ok_or_else(|| AppError::Invalid("需要有起点的时间范围，如 7d / 30d".into()))?;
+    let upper = compute_time_upper(&time_range).unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let config = AppConfig::load().unwrap_or_default();
+
+    let (pages, earliest) = load_first_seen_pages(&config)?;
+    let snapshots_used = earliest.used;
+    let mut sites = crate::discovery::new_sites(&pages, &earliest, lower, upper);
+    let mut new_pages = crate::discovery::new_pages(&pages, lower, upper);
+    let (total_sites, total_pages) = (sites.len() as i64, new_pages.len() as i64);
+    sites.truncate(limit as usize);
+    new_pages.truncate(limit as usize);
+
+    Ok(redact::apply(DiscoveryResponse {
+        sites,
+        pages: new_pages,
+        total_sites,
+        total_pages,
+        snapshots_used: snapshots_used as i64,
+    }))
+}
+
+pub fn generate_report(time_range: Option<String>, format: String) -> AppResult<String> {
+    use crate::report::{self, ReportFormat};
+
+    let format = ReportFormat::parse(&format)
+        .ok_or_else(|| AppError::Invalid("format 仅支持 html / markdown".into()))?;
+    let content = report::render(&digest_report(time_range)?, format);
+
+    let report_dir = AppConfig::get_export_dir()
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .join("reports");
+    std::fs::create_dir_all(&report_dir)
+        .map_err(|e| AppError::Internal(format!("创建报告目录失败: {}", e)))?;
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let target_path = report_dir.join(format!("digest_{}.{}", timestamp, format.extension()));
+    std::fs::write(&target_path, content)
+        .map_err(|e| AppError::Internal(format!("写入报告失败: {}", e)))?;
+
+    Ok(target_path.to_string_lossy().to_string())
+}
+
+// 配置相关命令
+pub fn get_config() -> AppResult<AppConfig> {
+    AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))
+}
+
+pub fn set_db_path(path: String) -> AppResult<String> {
+    // 验证路径
+    AppConfig::validate_db_path(&path)
+        .map_err(|e| AppError::Invalid(format!("数据库路径验证失败: {}", e)))?;
+
+    // 加载配置并设置新路径
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_db_path(path.clone())
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    // 重新初始化数据库连接
+    crate::db::reset_connection(&path).map_err(|e| AppError::Db(e.to_string()))?;
+
+    Ok("数据库路径设置成功".to_string())
+}
+
+pub fn validate_db_path(path: String) -> AppResult<bool> {
+    AppConfig::validate_db_path(&path).map_err(|e| AppError::Invalid(e.to_string()))
+}
+
+pub fn copy_browser_db_to_app(source_path: String) -> AppResult<String> {
+    use std::fs;
+    use std::path::Path;
+
+    if !Path::new(&source_path).exists() {
+        return Err(AppError::Invalid("源数据库文件不存在".to_string()));
+    }
+    // 启用加密后导入的副本同样加密保存，锁定状态下拒绝导入，避免在应用目录留下明文
+    let vault_key = if encrypted_db_path().is_ok() {
+        Some(
+            crate::vault::current_key()
+                .ok_or_else(|| AppError::Invalid("数据库已加密，请先解锁后再导入".into()))?,
+        )
+    } else {
+        None
+    };
+
+    let app_dir = AppConfig::get_app_dir().map_err(|e| AppError::Internal(e.to_string()))?;
+    fs::create_dir_all(&app_dir)
+        .map_err(|e| AppError::Internal(format!("创建应用目录失败: {}", e)))?;
+
+    // 生成目标文件名 (带时间戳的history.db)
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let target_filename = format!("history_{}.db", timestamp);
+    let target_path = app_dir.join(&target_filename);
+
+    fs::copy(&source_path, &target_path)
+        .map_err(|e| AppError::Internal(format!("复制数据库文件失败: {}", e)))?;
+
+    if let Err(e) = AppConfig::validate_db_path(target_path.to_string_lossy().as_ref()) {
+        crate::vault::wipe_db_files(&target_path);
+        return Err(AppError::Invalid(format!("复制的数据库文件无效: {}", e)));
+    }
+
+    match vault_key {
+        Some(key) => crate::vault::encrypt_file(&key, &target_path)
+            .map_err(|e| AppError::Internal(format!("加密导入的数据库失败: {}", e))),
+        None => Ok(target_path.to_string_lossy().to_string()),
+    }
+}
+
+// 应用目录中仍为明文的旧快照（history_*.db，不含当前数据库）
+fn plaintext_snapshots() -> AppResult<Vec<std::path::PathBuf>> {
+    let config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    let app_dir = AppConfig::get_app_dir().map_err(|e| AppError::Internal(e.to_string()))?;
+    Ok(
+        crate::discovery::snapshot_paths(&app_dir, config.get_db_path().as_deref())
+            .into_iter()
+            .filter(|p| !crate::vault::is_encrypted_file(p))
+            .collect(),
+    )
+}
+
+// 用当前解锁的密钥加密明文快照并擦除原文件
+pub fn encrypt_snapshots() -> AppResult<String> {
+    encrypted_db_path()?;
+    let key = crate::vault::current_key()
+        .ok_or_else(|| AppError::Invalid("数据库已锁定，请先解锁".into()))?;
+    let snapshots = plaintext_snapshots()?;
+    for path in &snapshots {
+        crate::vault::encrypt_file(&key, path)
+            .map_err(|e| AppError::Internal(format!("加密快照失败 {:?}: {}", path, e)))?;
+    }
+    Ok(format!("已加密 {} 个明文快照", snapshots.len()))
+}
+
+// 擦除明文快照（首次访问时间的快照修正将不再使用它们）
+pub fn wipe_snapshots() -> AppResult<String> {
+    let snapshots = plaintext_snapshots()?;
+    for path in &snapshots {
+        crate::vault::wipe_db_files(path);
+    }
+    Ok(format!("已擦除 {} 个明文快照", snapshots.len()))
+}
+
+pub fn set_browser_db_path(path: String) -> AppResult<String> {
+    // 验证路径
+    if !std::path::Path::new(&path).exists() {
+        return Err(AppError::Invalid("浏览器数据库文件不存在".to_string()));
+    }
+
+    // 加载配置并设置浏览器数据库路径
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_browser_db_path(path)
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    Ok("浏览器数据库路径保存成功".to_string())
+}
+
+pub fn cleanup_old_dbs() -> AppResult<String> {
+    use std::fs;
+    use std::path::Path;
+
+    let config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let db_path = config
+        .get_db_path()
+        .ok_or_else(|| AppError::Invalid("未配置数据库路径".to_string()))?;
+
+    let current_db = Path::new(&db_path);
+    let parent_dir = current_db
+        .parent()
+        .ok_or_else(|| AppError::Invalid("无法获取数据库文件所在目录".to_string()))?;
+
+    let current_filename = current_db
+        .file_name()
+        .ok_or_else(|| AppError::Invalid("无法获取当前数据库文件名".to_string()))?;
+
+    // 获取当前数据库的基础名称（不包含扩展名）
+    let current_basename = current_db
+        .file_stem()
+        .ok_or_else(|| AppError::Invalid("无法获取当前数据库基础名称".to_string()))?;
+
+    // 读取目录中的所有文件
+    let entries =
+        fs::read_dir(parent_dir).map_err(|e| AppError::Internal(format!("读取目录失败: {}", e)))?;
+
+    let mut deleted_count = 0;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::Internal(format!("读取目录项失败: {}", e)))?;
+        let entry_path = entry.path();
+
+        // 检查是否是.db/.db-shm/.db-wal文件
+        if let Some(extension) = entry_path.extension() {
+            if extension == "db" || extension == "db-shm" || extension == "db-wal" {
+                // 检查是否是当前使用的数据库相关文件
+                if let Some(file_name) = entry_path.file_name() {
+                    let file_name_str = file_name.to_string_lossy();
+                    let current_basename_str = current_basename.to_string_lossy();
+                    let is_current_db_file = file_name == current_filename
+                        || file_name_str == format!("{}.db-shm", current_basename_str)
+                        || file_name_str == format!("{}.db-wal", current_basename_str);
+
+                    if !is_current_db_file {
+                        if let Err(e) = fs::remove_file(&entry_path) {
+                            eprintln!("删除文件失败 {:?}: {}", entry_path, e);
+                        } else {
+                            deleted_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(format!("已清理 {} 个旧数据库文件", deleted_count))
+}
+
+pub fn set_top_sites_count(count: u32) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_top_sites_count(count)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok(format!("TOP站点数量已设置为 {}", count))
+}
+
+pub fn set_frecency_half_life(days: f64) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_frecency_half_life_days(days)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok(format!("相关度半衰期已设置为 {} 天", days))
+}
+
+pub fn set_session_gap_minutes(minutes: u32) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_session_gap_minutes(minutes)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok(format!("会话间隔已设置为 {} 分钟", minutes))
+}
+
+pub fn set_url_canonical_rules(rules: CanonicalRules) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_url_canonical_rules(rules.clone())
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+    // 当前连接上的 SQL 函数需按新规则重新注册
+    with_conn(|conn| crate::canonical::register(conn, rules))?;
+
+    Ok("URL 规范化规则已更新".into())
+}
+
+pub fn set_redaction_config(redaction: RedactionConfig) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_redaction(redaction)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok("脱敏设置已更新".into())
+}
+
+pub fn set_blocklist(blocklist: Blocklist) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_blocklist(blocklist.clone())
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+    // 重新注册过滤函数，并重建不含排除项的搜索建议索引
+    with_conn(|conn| {
+        crate::exclusion::register(conn, &blocklist)?;
+        crate::suggest::rebuild(conn);
+        Ok(())
+    })?;
+
+    Ok("排除列表已更新".into())
+}
+
+pub fn set_category_rules(rules: CategoryRules) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_category_rules(rules.clone())
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+    // 重新注册分类函数，之后的统计立即按新规则归类
+    with_conn(|conn| crate::categories::register(conn, &rules))?;
+
+    Ok("分类规则已更新".into())
+}
+
+pub fn set_search_engine_rules(rules: Vec<SearchEngineRule>) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    let count = rules.len();
+    config
+        .set_search_engine_rules(rules)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok(format!("已保存 {} 条自定义搜索规则", count))
+}
+
+fn encrypted_db_path() -> AppResult<String> {
+    let config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    config
+        .get_db_path()
+        .filter(|p| p.ends_with(crate::vault::ENCRYPTED_SUFFIX))
+        .ok_or_else(|| AppError::Invalid("当前数据库未加密".into()))
+}
+
+pub fn encrypt_database(passphrase: String) -> AppResult<String> {
+    use crate::vault;
+    use std::path::Path;
+
+    let key = vault::VaultKey::derive(&passphrase).map_err(|e| AppError::Invalid(e.to_string()))?;
+    let db_path = with_conn(|conn| {
+        Ok(conn
+            .path()
+            .filter(|p| !p.is_empty() && crate::db::is_app_owned(conn))
+            .map(|p| p.to_string()))
+    })?
+    .ok_or_else(|| AppError::Invalid("只能加密应用目录中的数据库副本".into()))?;
+
+    let plaintext = crate::db::serialize_current()?;
+    let encrypted =
+        vault::encrypt(&key, &plaintext).map_err(|e| AppError::Internal(e.to_string()))?;
+    let target = format!("{}{}", db_path, vault::ENCRYPTED_SUFFIX);
+    vault::write_atomic(Path::new(&target), &encrypted)
+        .map_err(|e| AppError::Internal(format!("写入加密文件失败: {}", e)))?;
+
+    // 清除磁盘上的明文汇总，关闭明文连接后擦除明文文件及其 WAL
+    with_conn(crate::rollup::forget_current)?;
+    crate::db::close_connection();
+    vault::wipe_db_files(Path::new(&db_path));
+
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    config
+        .set_db_path(target.clone())
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    vault::start_session(key);
+    crate::db::open_decrypted(&plaintext)?;
+
+    Ok(target)
+}
+
+pub fn unlock_database(passphrase: String) -> AppResult<String> {
+    let path = encrypted_db_path()?;
+    let data =
+        std::fs::read(&path).map_err(|e| AppError::Internal(format!("读取加密文件失败: {}", e)))?;
+    let (key, plaintext) =
+        crate::vault::decrypt(&passphrase, &data).map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    crate::vault::start_session(key);
+    crate::db::open_decrypted(&plaintext)?;
+    Ok("数据库已解锁".into())
+}
+
+pub fn lock_database() -> AppResult<String> {
+    encrypted_db_path()?;
+    crate::db::lock();
+    Ok("数据库已锁定".into())
+}
+
+pub fn change_passphrase(old_passphrase: String, new_passphrase: String) -> AppResult<String> {
+    use crate::vault;
+    use std::path::{Path, PathBuf};
+
+    let path = encrypted_db_path()?;
+    let read = |path: &std::path::Path| {
+        std::fs::read(path)
+            .map_err(|e| AppError::Internal(format!("读取加密文件失败 {:?}: {}", path, e)))
+    };
+    let (old_key, plaintext) = vault::decrypt(&old_passphrase, &read(Path::new(&path))?)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    // 新 salt 派生新密钥，数据库与全部加密快照一起重新加密后整体替换
+    let new_key =
+        vault::VaultKey::derive(&new_passphrase).map_err(|e| AppError::Invalid(e.to_string()))?;
+    let reencrypt = |plaintext: &[u8]| {
+        vault::encrypt(&new_key, plaintext).map_err(|e| AppError::Internal(e.to_string()))
+    };
+    let mut files = vec![(PathBuf::from(&path), reencrypt(&plaintext)?)];
+    let app_dir = AppConfig::get_app_dir().map_err(|e| AppError::Internal(e.to_string()))?;
+    for snapshot in crate::discovery::encrypted_snapshot_paths(&app_dir, Some(&path)) {
+        let data = read(&snapshot)?;
+        let plaintext = vault::decrypt_with_key(&old_key, &data)
+            .or_else(|_| vault::decrypt(&old_passphrase, &data).map(|(_, p)| p))
+            .map_err(|e| AppError::Invalid(format!("无法解密快照 {:?}: {}", snapshot, e)))?;
+        files.push((snapshot, reencrypt(&plaintext)?));
+    }
+    vault::replace_all(&files).map_err(|e| AppError::Internal(e.to_string()))?;
+    if vault::is_unlocked() {
+        vault::start_session(new_key);
+    }
+
+    Ok("密码已更新".into())
+}
+
+pub fn lock_status() -> AppResult<LockStatus> {
+    let config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    let encrypted = config
+        .get_db_path()
+        .is_some_and(|p| p.ends_with(crate::vault::ENCRYPTED_SUFFIX));
+
+    Ok(LockStatus {
+        encrypted,
+        locked: encrypted && !crate::vault::is_unlocked(),
+        auto_lock_minutes: config.auto_lock_minutes,
+    })
+}
+
+pub fn set_auto_lock_minutes(minutes: u32) -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+
+    config
+        .set_auto_lock_minutes(minutes)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    Ok(format!("自动锁定时间已设置为 {} 分钟", minutes))
+}
+
+pub fn start_api_server(port: Option<u16>) -> AppResult<ApiServerStatus> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    let port = port.unwrap_or(config.api_server_port);
+    if port < 1024 {
+        return Err(AppError::Invalid("端口必须在1024-65535之间".into()));
+    }
+
+    // 端口绑定成功后才写入配置，否则下次启动会反复尝试
+    crate::server::start(port)?;
+    if let Err(e) = config.set_api_server(true, port) {
+        crate::server::stop();
+        return Err(AppError::Internal(e.to_string()));
+    }
+    Ok(crate::server::status())
+}
+
+pub fn stop_api_server() -> AppResult<String> {
+    let mut config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+    let port = config.api_server_port;
+
+    config
+        .set_api_server(false, port)
+        .map_err(|e| AppError::Invalid(e.to_string()))?;
+    crate::server::stop();
+
+    Ok("HTTP 服务已停止".into())
+}
+
+pub fn api_server_status() -> AppResult<ApiServerStatus> {
+    Ok(crate::server::status())
+}
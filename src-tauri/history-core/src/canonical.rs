@@ -0,0 +1,189 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// URL 规范化规则：用于将仅因追踪参数、锚点、结尾斜杠、协议不同而重复的页面归并
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanonicalRules {
+    // 需要移除的查询参数名，不区分大小写；以 * 结尾表示前缀匹配，如 utm_*
+    #[serde(default = "default_strip_params")]
+    pub strip_params: Vec<String>,
+    #[serde(default = "default_true")]
+    pub drop_fragment: bool,
+    #[serde(default = "default_true")]
+    pub strip_trailing_slash: bool,
+    // http 与 https 视为同一页面
+    #[serde(default = "default_true")]
+    pub unify_scheme: bool,
+    #[serde(default)]
+    pub strip_www: bool,
+    // 查询参数按名称排序，忽略参数顺序差异
+    #[serde(default = "default_true")]
+    pub sort_params: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_strip_params() -> Vec<String> {
+    [
+        "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid",
+        "mc_eid", "igshid", "_ga", "_gl", "spm",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+impl Default for CanonicalRules {
+    fn default() -> Self {
+        Self {
+            strip_params: default_strip_params(),
+            drop_fragment: true,
+            strip_trailing_slash: true,
+            unify_scheme: true,
+            strip_www: false,
+            sort_params: true,
+        }
+    }
+}
+
+impl CanonicalRules {
+    fn strips(&self, name: &str) -> bool {
+        self.strip_params
+            .iter()
+            .any(|rule| match rule.strip_suffix('*') {
+                // 按字节切片前需确认落在字符边界上，参数名可能含非 ASCII 字符
+                Some(prefix) => name
+                    .get(..prefix.len())
+                    .is_some_and(|head| head.eq_ignore_ascii_case(prefix)),
+                None => name.eq_ignore_ascii_case(rule),
+            })
+    }
+}
+
+// 非 http(s) 或无法解析的 URL 原样返回
+pub fn canonicalize(raw: &str, rules: &CanonicalRules) -> String {
+    let mut url = match Url::parse(raw) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") => u,
+        _ => return raw.to_string(),
+    };
+
+    if rules.unify_scheme && url.scheme() == "http" {
+        let _ = url.set_scheme("https");
+    }
+    if rules.strip_www {
+        if let Some(bare) = url.host_str().and_then(|h| h.strip_prefix("www.")) {
+            let bare = bare.to_string();
+            let _ = url.set_host(Some(&bare));
+        }
+    }
+    if rules.drop_fragment {
+        url.set_fragment(None);
+    }
+
+    if url.query().is_some() {
+        let mut pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(k, _)| !rules.strips(k))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        if rules.sort_params {
+            pairs.sort();
+        }
+        if pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+
+    if rules.strip_trailing_slash {
+        let path = url.path().trim_end_matches('/').to_string();
+        url.set_path(&path);
+    }
+    url.into()
+}
+
+// 注册 SQL 函数 canonical_url(url)；规则变更后需重新注册
+pub fn register(conn: &Connection, rules: CanonicalRules) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "canonical_url",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let url = ctx.get::<Option<String>>(0)?.unwrap_or_default();
+            Ok(canonicalize(&url, &rules))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_ascii_param_name_does_not_panic() {
+        let rules = CanonicalRules::default();
+        assert_eq!(
+            canonicalize("https://a.com/p?utm%C3%A9=1&%C3%A9=2", &rules),
+            "https://a.com/p?utm%C3%A9=1&%C3%A9=2"
+        );
+        assert_eq!(
+            canonicalize("https://a.com/p?%E4%B8%AD=1", &rules),
+            "https://a.com/p?%E4%B8%AD=1"
+        );
+    }
+
+    #[test]
+    fn prefix_and_exact_rules_strip_params() {
+        let rules = CanonicalRules::default();
+        assert_eq!(
+            canonicalize(
+                "https://a.com/p?UTM_Source=x&utm_medium=y&id=3&fbclid=z",
+                &rules
+            ),
+            "https://a.com/p?id=3"
+        );
+        assert_eq!(
+            canonicalize("https://a.com/p?utm=1", &rules),
+            "https://a.com/p?utm=1"
+        );
+        assert_eq!(
+            canonicalize("https://a.com/p?gclid=1", &rules),
+            "https://a.com/p"
+        );
+    }
+
+    #[test]
+    fn order_slash_scheme_and_fragment_are_normalised() {
+        let rules = CanonicalRules::default();
+        let a = canonicalize("http://a.com/docs/?b=2&a=1#top", &rules);
+        let b = canonicalize("https://a.com/docs?a=1&b=2", &rules);
+        assert_eq!(a, "https://a.com/docs?a=1&b=2");
+        assert_eq!(a, b);
+        assert_eq!(canonicalize("https://a.com/", &rules), "https://a.com/");
+
+        let keep = CanonicalRules {
+            sort_params: false,
+            strip_trailing_slash: false,
+            ..CanonicalRules::default()
+        };
+        assert_eq!(
+            canonicalize("https://a.com/docs/?b=2&a=1", &keep),
+            "https://a.com/docs/?b=2&a=1"
+        );
+    }
+
+    #[test]
+    fn non_http_urls_are_unchanged() {
+        let rules = CanonicalRules::default();
+        assert_eq!(
+            canonicalize("chrome://settings/?utm_x=1", &rules),
+            "chrome://settings/?utm_x=1"
+        );
+        assert_eq!(canonicalize("not a url", &rules), "not a url");
+    }
+}
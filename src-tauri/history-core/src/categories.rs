@@ -0,0 +1,218 @@
+use crate::exclusion::Blocklist;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+// 站点分类规则：按域名 / URL 通配符 / 标题关键字归类，匹配方式与排除列表相同。
+// 查询时通过 SQL 函数 url_category(url, title) 应用，浏览器原生数据库也能按类别统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRule {
+    pub category: String,
+    #[serde(flatten)]
+    pub pattern: Blocklist,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryRules {
+    // 是否在自定义规则之后继续使用内置规则
+    #[serde(default = "default_use_defaults")]
+    pub use_defaults: bool,
+    // 按顺序匹配，先命中者生效
+    #[serde(default)]
+    pub rules: Vec<CategoryRule>,
+}
+
+fn default_use_defaults() -> bool {
+    true
+}
+
+impl Default for CategoryRules {
+    fn default() -> Self {
+        Self {
+            use_defaults: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+fn rule(category: &str, domains: &[&str], url_globs: &[&str]) -> CategoryRule {
+    let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+    CategoryRule {
+        category: category.to_string(),
+        pattern: Blocklist {
+            domains: owned(domains),
+            url_globs: owned(url_globs),
+            title_keywords: Vec::new(),
+        },
+    }
+}
+
+// 内置规则；docs 放在 dev 之前，使 docs.github.com 之类归为文档
+pub fn default_rules() -> Vec<CategoryRule> {
+    vec![
+        rule(
+            "docs",
+            &[
+                "docs.rs",
+                "doc.rust-lang.org",
+                "developer.mozilla.org",
+                "readthedocs.io",
+                "readthedocs.org",
+                "learn.microsoft.com",
+                "docs.python.org",
+                "devdocs.io",
+                "cppreference.com",
+            ],
+            &["*://docs.*", "*/docs/*", "*/documentation/*"],
+        ),
+        rule(
+            "dev",
+            &[
+                "github.com",
+                "gitlab.com",
+                "bitbucket.org",
+                "gitee.com",
+                "stackoverflow.com",
+                "stackexchange.com",
+                "crates.io",
+                "npmjs.com",
+                "pypi.org",
+                "hub.docker.com",
+                "leetcode.com",
+                "leetcode.cn",
+            ],
+            &[],
+        ),
+        rule(
+            "news",
+            &[
+                "news.ycombinator.com",
+                "news.google.com",
+                "bbc.com",
+                "bbc.co.uk",
+                "cnn.com",
+                "nytimes.com",
+                "reuters.com",
+                "theguardian.com",
+                "apnews.com",
+                "36kr.com",
+                "thepaper.cn",
+                "news.qq.com",
+                "news.sina.com.cn",
+                "news.163.com",
+            ],
+            &["*://news.*"],
+        ),
+        rule(
+            "social",
+            &[
+                "twitter.com",
+                "x.com",
+                "facebook.com",
+                "instagram.com",
+                "linkedin.com",
+                "reddit.com",
+                "threads.net",
+                "mastodon.social",
+                "weibo.com",
+                "zhihu.com",
+                "douban.com",
+                "tieba.baidu.com",
+                "xiaohongshu.com",
+            ],
+            &[],
+        ),
+        rule(
+            "video",
+            &[
+                "youtube.com",
+                "youtu.be",
+                "bilibili.com",
+                "netflix.com",
+                "twitch.tv",
+                "vimeo.com",
+                "tiktok.com",
+                "douyin.com",
+                "iqiyi.com",
+                "youku.com",
+                "v.qq.com",
+            ],
+            &[],
+        ),
+        rule(
+            "shopping",
+            &[
+                "amazon.com",
+                "amazon.co.uk",
+                "amazon.de",
+                "amazon.co.jp",
+                "ebay.com",
+                "aliexpress.com",
+                "etsy.com",
+                "walmart.com",
+                "taobao.com",
+                "tmall.com",
+                "jd.com",
+                "pinduoduo.com",
+            ],
+            &[],
+        ),
+    ]
+}
+
+pub struct Classifier {
+    rules: Vec<(String, Blocklist)>,
+}
+
+impl Classifier {
+    pub fn new(config: &CategoryRules) -> Self {
+        let defaults = if config.use_defaults {
+            default_rules()
+        } else {
+            Vec::new()
+        };
+        let rules = config
+            .rules
+            .iter()
+            .chain(defaults.iter())
+            .map(|r| (r.category.trim().to_string(), r.pattern.normalized()))
+            .filter(|(category, pattern)| !category.is_empty() && !pattern.is_empty())
+            .collect();
+        Self { rules }
+    }
+
+    pub fn classify(&self, url: &str, title: Option<&str>) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|(_, pattern)| pattern.matches(url, title))
+            .map(|(category, _)| category.as_str())
+    }
+}
+
+// 注册 SQL 函数 url_category(url, title)，未命中返回 NULL；规则变更后需重新注册
+pub fn register(conn: &Connection, rules: &CategoryRules) -> rusqlite::Result<()> {
+    let classifier = Classifier::new(rules);
+    conn.create_scalar_function(
+        "url_category",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let url = ctx.get::<Option<String>>(0)?.unwrap_or_default();
+            let title = ctx.get::<Option<String>>(1)?;
+            Ok(classifier
+                .classify(&url, title.as_deref())
+                .map(str::to_string))
+        },
+    )
+}
+
+// product_entities 的类别优先，其次按分类规则
+pub fn sql_expr(entity_category: Option<&str>, url_col: &str, title_col: &str) -> String {
+    match entity_category {
+        Some(col) => format!(
+            "COALESCE(NULLIF({}, ''), url_category({}, {}))",
+            col, url_col, title_col
+        ),
+        None => format!("url_category({}, {})", url_col, title_col),
+    }
+}
@@ -0,0 +1,140 @@
+use crate::domain::{SiteVisits, VisitChange};
+use std::collections::HashMap;
+
+// 两个时间范围的对比：A 为基准，B 为对比对象，变化量均为 B - A
+
+// 按访问次数降序排名，次数相同按名称排序，名次从 1 开始
+fn ranks(counts: &HashMap<String, i64>) -> HashMap<&str, u32> {
+    let mut items: Vec<(&str, i64)> = counts.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, (k, _))| (k, i as u32 + 1))
+        .collect()
+}
+
+// 两边任一出现的条目，按两期中较大的访问次数排序取前 top_n
+pub fn changes(
+    a: &HashMap<String, i64>,
+    b: &HashMap<String, i64>,
+    top_n: usize,
+) -> Vec<VisitChange> {
+    let (ranks_a, ranks_b) = (ranks(a), ranks(b));
+    let mut keys: Vec<&String> = a
+        .keys()
+        .chain(b.keys().filter(|k| !a.contains_key(*k)))
+        .collect();
+    let peak = |k: &String| {
+        a.get(k)
+            .copied()
+            .unwrap_or(0)
+            .max(b.get(k).copied().unwrap_or(0))
+    };
+    keys.sort_by(|x, y| peak(y).cmp(&peak(x)).then_with(|| x.cmp(y)));
+    keys.truncate(top_n);
+
+    keys.into_iter()
+        .map(|key| {
+            let visits_a = a.get(key).copied().unwrap_or(0);
+            let visits_b = b.get(key).copied().unwrap_or(0);
+            let rank_a = ranks_a.get(key.as_str()).copied();
+            let rank_b = ranks_b.get(key.as_str()).copied();
+            VisitChange {
+                key: key.clone(),
+                visits_a,
+                visits_b,
+                change: visits_b - visits_a,
+                // 基准期为 0 时相对变化无意义
+                change_pct: (visits_a > 0)
+                    .then(|| (visits_b - visits_a) as f64 / visits_a as f64 * 100.0),
+                rank_a,
+                rank_b,
+                // 正数表示名次上升
+                rank_change: rank_a.zip(rank_b).map(|(ra, rb)| ra as i64 - rb as i64),
+            }
+        })
+        .collect()
+}
+
+// 只在 from 中出现、不在 other 中出现的站点，按访问次数降序
+pub fn only_in(
+    from: &HashMap<String, i64>,
+    other: &HashMap<String, i64>,
+    top_n: usize,
+) -> Vec<SiteVisits> {
+    let mut items: Vec<SiteVisits> = from
+        .iter()
+        .filter(|(k, _)| !other.contains_key(*k))
+        .map(|(site, visits)| SiteVisits {
+            site: site.clone(),
+            visits: *visits,
+        })
+        .collect();
+    items.sort_by(|x, y| y.visits.cmp(&x.visits).then_with(|| x.site.cmp(&y.site)));
+    items.truncate(top_n);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(items: &[(&str, i64)]) -> HashMap<String, i64> {
+        items.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+    }
+
+    #[test]
+    fn rank_movement_and_relative_change() {
+        let a = counts(&[("a.com", 10), ("b.com", 5), ("c.com", 5), ("gone.com", 1)]);
+        let b = counts(&[("a.com", 4), ("b.com", 20), ("c.com", 5), ("fresh.com", 8)]);
+        let changes = changes(&a, &b, 10);
+        let keys: Vec<&str> = changes.iter().map(|c| c.key.as_str()).collect();
+        // 按两期较大值排序
+        assert_eq!(
+            keys,
+            vec!["b.com", "a.com", "fresh.com", "c.com", "gone.com"]
+        );
+
+        let by_key = |key: &str| changes.iter().find(|c| c.key == key).unwrap();
+        let b_com = by_key("b.com");
+        assert_eq!(
+            (b_com.rank_a, b_com.rank_b, b_com.rank_change),
+            (Some(2), Some(1), Some(1))
+        );
+        assert_eq!(b_com.change_pct, Some(300.0));
+        let a_com = by_key("a.com");
+        assert_eq!(
+            (a_com.rank_a, a_com.rank_b, a_com.rank_change),
+            (Some(1), Some(4), Some(-3))
+        );
+        assert_eq!(a_com.change, -6);
+        // 同次数按名称排名
+        assert_eq!(by_key("c.com").rank_a, Some(3));
+        let fresh = by_key("fresh.com");
+        assert_eq!(
+            (fresh.rank_a, fresh.rank_b, fresh.rank_change),
+            (None, Some(2), None)
+        );
+        assert_eq!(fresh.change_pct, None);
+        let gone = by_key("gone.com");
+        assert_eq!(
+            (gone.visits_b, gone.rank_b, gone.change_pct),
+            (0, None, Some(-100.0))
+        );
+
+        assert_eq!(super::changes(&a, &b, 2).len(), 2);
+    }
+
+    #[test]
+    fn only_in_lists_sites_missing_from_the_other_period() {
+        let a = counts(&[("a.com", 1), ("x.com", 3), ("y.com", 3)]);
+        let b = counts(&[("a.com", 9)]);
+        let sites: Vec<(String, i64)> = only_in(&a, &b, 10)
+            .into_iter()
+            .map(|s| (s.site, s.visits))
+            .collect();
+        assert_eq!(sites, vec![("x.com".into(), 3), ("y.com".into(), 3)]);
+        assert!(only_in(&b, &a, 10).is_empty());
+    }
+}
@@ -0,0 +1,348 @@
+use crate::canonical::CanonicalRules;
+use crate::categories::CategoryRules;
+use crate::exclusion::Blocklist;
+use crate::redact::RedactionConfig;
+use crate::search_queries::SearchEngineRule;
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// AppConfig的全局内存缓存，以避免频繁的磁盘读取
+static GLOBAL_CONFIG: OnceCell<Mutex<AppConfig>> = OnceCell::new();
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppConfig {
+    pub db_path: Option<String>,
+    pub browser_db_path: Option<String>,
+    #[serde(default = "default_top_sites_count")]
+    pub top_sites_count: u32,
+    #[serde(default = "default_frecency_half_life_days")]
+    pub frecency_half_life_days: f64,
+    #[serde(default = "default_session_gap_minutes")]
+    pub session_gap_minutes: u32,
+    // 用户自定义搜索引擎 / 内部站点搜索规则，优先于内置规则
+    #[serde(default)]
+    pub search_engine_rules: Vec<SearchEngineRule>,
+    // 重复页面归并使用的 URL 规范化规则
+    #[serde(default)]
+    pub url_canonical_rules: CanonicalRules,
+    // 输出与导出中的敏感 URL 参数 / 标题脱敏
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    // 排除列表：域名 / URL 通配符 / 标题关键字
+    #[serde(default)]
+    pub blocklist: Blocklist,
+    // 站点分类规则：域名 / URL 通配符 / 标题关键字 → 类别
+    #[serde(default)]
+    pub category_rules: CategoryRules,
+    // 加密数据库空闲自动锁定时间（分钟），0 表示不自动锁定
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u32,
+    // 本地 HTTP/JSON 接口，仅监听 127.0.0.1
+    #[serde(default)]
+    pub api_server_enabled: bool,
+    #[serde(default = "default_api_server_port")]
+    pub api_server_port: u16,
+    pub last_updated: i64,
+}
+
+fn default_top_sites_count() -> u32 {
+    6
+}
+
+fn default_frecency_half_life_days() -> f64 {
+    crate::frecency::DEFAULT_HALF_LIFE_DAYS
+}
+
+fn default_session_gap_minutes() -> u32 {
+    crate::sessions::DEFAULT_GAP_MINUTES
+}
+
+fn default_auto_lock_minutes() -> u32 {
+    15
+}
+
+fn default_api_server_port() -> u16 {
+    crate::server::DEFAULT_PORT
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            db_path: None,
+            browser_db_path: None,
+            top_sites_count: 6,
+            frecency_half_life_days: default_frecency_half_life_days(),
+            session_gap_minutes: default_session_gap_minutes(),
+            search_engine_rules: Vec::new(),
+            url_canonical_rules: CanonicalRules::default(),
+            redaction: RedactionConfig::default(),
+            blocklist: Blocklist::default(),
+            category_rules: CategoryRules::default(),
+            auto_lock_minutes: default_auto_lock_minutes(),
+            api_server_enabled: false,
+            api_server_port: default_api_server_port(),
+            last_updated: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+impl AppConfig {
+    fn config_file_path() -> Result<PathBuf> {
+        let mut app_dir =
+            dirs_next::config_dir().ok_or_else(|| anyhow::anyhow!("无法获取应用配置目录"))?;
+        app_dir.push("BrowserHistoryBrowser");
+        if !app_dir.exists() {
+            fs::create_dir_all(&app_dir)?;
+        }
+
+        Ok(app_dir.join("config.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        if let Some(m) = GLOBAL_CONFIG.get() {
+            let cfg = m.lock().unwrap();
+            return Ok(cfg.clone());
+        }
+
+        let config_path = Self::config_file_path()?;
+
+        let config = if !config_path.exists() {
+            let default_config = Self::default();
+            let _ = serde_json::to_string_pretty(&default_config)?;
+            default_config.save()?;
+            default_config
+        } else {
+            let content = fs::read_to_string(&config_path)?;
+            let config: Self = serde_json::from_str(&content)?;
+            config
+        };
+        let _ = GLOBAL_CONFIG.set(Mutex::new(config.clone()));
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let config_path = Self::config_file_path()?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&config_path, content)?;
+        // 更新全局内存缓存（如果存在）
+        if let Some(m) = GLOBAL_CONFIG.get() {
+            let mut g = m.lock().unwrap();
+            *g = self.clone();
+        } else {
+            let _ = GLOBAL_CONFIG.set(Mutex::new(self.clone()));
+        }
+
+        Ok(())
+    }
+
+    pub fn set_db_path(&mut self, path: String) -> Result<()> {
+        // 验证路径是否存在
+        if !Path::new(&path).exists() {
+            return Err(anyhow::anyhow!("数据库文件不存在: {}", path));
+        }
+
+        self.db_path = Some(path);
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_db_path(&self) -> Option<String> {
+        self.db_path.clone()
+    }
+
+    pub fn set_browser_db_path(&mut self, path: String) -> Result<()> {
+        self.browser_db_path = Some(path);
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_top_sites_count(&mut self, count: u32) -> Result<()> {
+        if count == 0 || count > 50 {
+            return Err(anyhow::anyhow!("TOP站点数量必须在1-50之间"));
+        }
+        self.top_sites_count = count;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_frecency_half_life_days(&mut self, days: f64) -> Result<()> {
+        if !(1.0..=365.0).contains(&days) {
+            return Err(anyhow::anyhow!("半衰期必须在1-365天之间"));
+        }
+        self.frecency_half_life_days = days;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_session_gap_minutes(&mut self, minutes: u32) -> Result<()> {
+        if minutes == 0 || minutes > 1440 {
+            return Err(anyhow::anyhow!("会话间隔必须在1-1440分钟之间"));
+        }
+        self.session_gap_minutes = minutes;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_search_engine_rules(&mut self, rules: Vec<SearchEngineRule>) -> Result<()> {
+        for rule in &rules {
+            if rule.name.trim().is_empty() || rule.host.trim().is_empty() || rule.params.is_empty()
+            {
+                return Err(anyhow::anyhow!("搜索规则需要填写名称、域名和参数名"));
+            }
+        }
+        self.search_engine_rules = rules;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_url_canonical_rules(&mut self, rules: CanonicalRules) -> Result<()> {
+        if rules
+            .strip_params
+            .iter()
+            .any(|p| p.trim().is_empty() || p == "*")
+        {
+            return Err(anyhow::anyhow!("参数规则不能为空或仅为通配符"));
+        }
+        self.url_canonical_rules = rules;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_redaction(&mut self, redaction: RedactionConfig) -> Result<()> {
+        crate::redact::Redactor::new(&redaction)
+            .map_err(|e| anyhow::anyhow!("脱敏正则无效: {}", e))?;
+        self.redaction = redaction;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_blocklist(&mut self, blocklist: Blocklist) -> Result<()> {
+        if blocklist
+            .url_globs
+            .iter()
+            .any(|g| !g.trim().is_empty() && g.trim().chars().all(|c| c == '*'))
+        {
+            return Err(anyhow::anyhow!("URL 通配符不能仅由 * 组成"));
+        }
+        if blocklist
+            .domains
+            .iter()
+            .any(|d| d.contains(['/', ' ', '?']))
+        {
+            return Err(anyhow::anyhow!("排除域名只需填写域名，如 bank.example.com"));
+        }
+        self.blocklist = blocklist;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_category_rules(&mut self, rules: CategoryRules) -> Result<()> {
+        for rule in &rules.rules {
+            if rule.category.trim().is_empty() {
+                return Err(anyhow::anyhow!("类别名称不能为空"));
+            }
+            if rule.pattern.normalized().is_empty() {
+                return Err(anyhow::anyhow!(
+                    "类别「{}」至少需要一个域名、URL 通配符或标题关键字",
+                    rule.category.trim()
+                ));
+            }
+            if rule
+                .pattern
+                .domains
+                .iter()
+                .any(|d| d.contains(['/', ' ', '?']))
+            {
+                return Err(anyhow::anyhow!("分类域名只需填写域名，如 github.com"));
+            }
+        }
+        self.category_rules = rules;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_auto_lock_minutes(&mut self, minutes: u32) -> Result<()> {
+        if minutes > 1440 {
+            return Err(anyhow::anyhow!("自动锁定时间必须在0-1440分钟之间"));
+        }
+        self.auto_lock_minutes = minutes;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn set_api_server(&mut self, enabled: bool, port: u16) -> Result<()> {
+        if port < 1024 {
+            return Err(anyhow::anyhow!("端口必须在1024-65535之间"));
+        }
+        self.api_server_enabled = enabled;
+        self.api_server_port = port;
+        self.last_updated = chrono::Utc::now().timestamp();
+        self.save()?;
+        Ok(())
+    }
+
+    pub fn get_app_dir() -> Result<PathBuf> {
+        let mut app_dir =
+            dirs_next::config_dir().ok_or_else(|| anyhow::anyhow!("无法获取应用数据目录"))?;
+        app_dir.push("BrowserHistoryBrowser");
+        if !app_dir.exists() {
+            fs::create_dir_all(&app_dir)?;
+        }
+
+        Ok(app_dir)
+    }
+
+    pub fn get_export_dir() -> Result<PathBuf> {
+        let export_dir = Self::get_app_dir()?.join("exports");
+        if !export_dir.exists() {
+            fs::create_dir_all(&export_dir)?;
+        }
+
+        Ok(export_dir)
+    }
+
+    pub fn validate_db_path(path: &str) -> Result<bool> {
+        let db_path = Path::new(path);
+
+        if !db_path.exists() {
+            return Err(anyhow::anyhow!("文件不存在"));
+        }
+
+        if !db_path.is_file() {
+            return Err(anyhow::anyhow!("路径不是文件"));
+        }
+
+        // 加密数据库在解锁时校验
+        if crate::vault::is_encrypted_file(db_path) {
+            return Ok(true);
+        }
+
+        // 简单验证是否为SQLite文件（检查文件头）
+        let file_content = fs::read(db_path)?;
+        if file_content.len() < 16 {
+            return Err(anyhow::anyhow!("文件太小，不是有效的数据库文件"));
+        }
+
+        let sqlite_header = b"SQLite format 3\0";
+        if &file_content[..16] != sqlite_header {
+            return Err(anyhow::anyhow!("不是有效的SQLite数据库文件"));
+        }
+
+        Ok(true)
+    }
+}
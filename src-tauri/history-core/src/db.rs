@@ -0,0 +1,233 @@
+use crate::canonical;
+use crate::categories;
+use crate::config::AppConfig;
+use crate::exclusion;
+use crate::frecency;
+use crate::host;
+use crate::rollup;
+use crate::source;
+use crate::suggest;
+use crate::vault;
+use once_cell::sync::Lazy;
+use rusqlite::serialize::OwnedData;
+use rusqlite::{ffi, params, Connection, DatabaseName};
+use std::path::Path;
+use std::ptr::NonNull;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+static CONN: Lazy<Mutex<Option<Connection>>> = Lazy::new(|| Mutex::new(None));
+
+fn get_or_create_connection() -> rusqlite::Result<()> {
+    let mut conn_guard = CONN.lock().unwrap();
+
+    if conn_guard.is_none() {
+        // 尝试从配置加载数据库路径
+        let config = AppConfig::load().unwrap_or_default();
+        let db_path = config.get_db_path().unwrap_or_else(|| {
+            AppConfig::get_app_dir()
+                .map(|app_dir| {
+                    app_dir
+                        .join("history_test.db")
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .unwrap_or_else(|_| "history_test.db".to_string())
+        });
+        // 加密数据库只能通过解锁打开
+        if db_path.ends_with(vault::ENCRYPTED_SUFFIX) {
+            return Err(locked_error());
+        }
+        let conn = Connection::open(&db_path)?;
+
+        if db_path.ends_with("history_test.db") {
+            init_schema(&conn)?;
+        }
+        configure_connection(&conn)?;
+
+        *conn_guard = Some(conn);
+    }
+
+    Ok(())
+}
+
+pub fn reset_connection(new_path: &str) -> rusqlite::Result<()> {
+    let mut conn_guard = CONN.lock().unwrap();
+
+    // 关闭现有连接
+    *conn_guard = None;
+
+    // 加密数据库：若当前密钥可解密则直接打开，否则保持锁定等待解锁
+    if vault::is_encrypted_file(Path::new(new_path)) {
+        let plaintext = vault::current_key().and_then(|key| {
+            let data = std::fs::read(new_path).ok()?;
+            vault::decrypt_with_key(&key, &data).ok()
+        });
+        match plaintext {
+            Some(plaintext) => *conn_guard = Some(open_from_bytes(&plaintext)?),
+            None => vault::end_session(),
+        }
+        return Ok(());
+    }
+
+    // 创建新连接
+    let conn = Connection::open(new_path)?;
+    configure_connection(&conn)?;
+
+    *conn_guard = Some(conn);
+    Ok(())
+}
+
+fn locked_error() -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        ffi::Error::new(ffi::SQLITE_AUTH),
+        Some("数据库已锁定，请先解锁".to_string()),
+    )
+}
+
+// 将解密后的数据库映像载入内存连接，明文不落盘
+pub(crate) fn open_from_bytes(bytes: &[u8]) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open_in_memory()?;
+    let len = bytes.len();
+    // SAFETY: 缓冲区由 sqlite3_malloc64 分配且长度为 len，所有权随 deserialize 交给 SQLite
+    let data = unsafe {
+        let ptr = NonNull::new(ffi::sqlite3_malloc64(len as u64) as *mut u8).ok_or_else(|| {
+            rusqlite::Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_NOMEM), None)
+        })?;
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), len);
+        // 内存数据库不支持 WAL，将文件头中的读写版本改回回滚日志模式
+        if len > 19 && bytes[18] == 2 && bytes[19] == 2 {
+            *ptr.as_ptr().add(18) = 1;
+            *ptr.as_ptr().add(19) = 1;
+        }
+        OwnedData::from_raw_nonnull(ptr, len)
+    };
+    conn.deserialize(DatabaseName::Main, data, false)?;
+    configure_connection(&conn)?;
+    Ok(conn)
+}
+
+pub fn open_decrypted(bytes: &[u8]) -> rusqlite::Result<()> {
+    let conn = open_from_bytes(bytes)?;
+    *CONN.lock().unwrap() = Some(conn);
+    Ok(())
+}
+
+// 当前数据库的完整映像，用于加密写回
+pub fn serialize_current() -> rusqlite::Result<Zeroizing<Vec<u8>>> {
+    with_conn(|conn| Ok(Zeroizing::new(conn.serialize(DatabaseName::Main)?.to_vec())))
+}
+
+pub fn close_connection() {
+    *CONN.lock().unwrap() = None;
+}
+
+// 关闭连接并清除内存中的密钥
+pub fn lock() {
+    close_connection();
+    vault::end_session();
+    crate::discovery::clear_snapshot_cache();
+}
+
+// 当前数据库是否为应用目录中的副本（加密数据库以配置中的文件路径为准）
+pub fn is_app_owned(conn: &Connection) -> bool {
+    let path = match conn.path().filter(|p| !p.is_empty()) {
+        Some(p) => p.to_string(),
+        None if vault::is_unlocked() => match AppConfig::load().ok().and_then(|c| c.db_path) {
+            Some(p) => p,
+            None => return false,
+        },
+        None => return false,
+    };
+    let app_dir = match AppConfig::get_app_dir() {
+        Ok(dir) => dir,
+        Err(_) => return false,
+    };
+    std::fs::canonicalize(path)
+        .ok()
+        .zip(std::fs::canonicalize(app_dir).ok())
+        .is_some_and(|(db, dir)| db.starts_with(dir))
+}
+
+fn configure_connection(conn: &Connection) -> rusqlite::Result<()> {
+    // 基础性能设置
+    conn.pragma_update(None, "journal_mode", "WAL").ok();
+    conn.pragma_update(None, "synchronous", "NORMAL").ok();
+    // 汇总库由多个连接（GUI / CLI / HTTP 服务）共享，写入冲突时等待而不是立即失败
+    conn.busy_timeout(std::time::Duration::from_secs(5)).ok();
+
+    // 浏览器原生数据库通过临时视图映射为 navigation_history
+    let kind = source::detect(conn)?;
+    source::install_compat_views(conn, kind)?;
+    frecency::register(conn)?;
+    host::register(conn)?;
+    let config = AppConfig::load().unwrap_or_default();
+    canonical::register(conn, config.url_canonical_rules)?;
+    exclusion::register(conn, &config.blocklist)?;
+    categories::register(conn, &config.category_rules)?;
+    rollup::attach(conn)?;
+
+    // 搜索建议前缀索引随连接一起构建
+    suggest::rebuild(conn);
+    Ok(())
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    // 仅创建演示表（若真实已有表，可删除此段或改为检测）
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS navigation_history (
+            url TEXT PRIMARY KEY,
+            id INTEGER,
+            title TEXT,
+            metadata TEXT,
+            last_visited_time INTEGER NOT NULL DEFAULT 0,
+            num_visits INTEGER NOT NULL DEFAULT 1,
+            product_entity_id TEXT,
+            locale TEXT,
+            titledata TEXT,
+            urldata TEXT,
+            page_profile TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_nav_last_time ON navigation_history(last_visited_time DESC);
+        "#,
+    )?;
+
+    // 若表为空，插入一些样例数据
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM navigation_history", [], |r| r.get(0))?;
+    if count == 0 {
+        let now = chrono::Utc::now().timestamp();
+        for i in 0..50 {
+            // 简单样例
+            conn.execute(
+                "INSERT OR REPLACE INTO navigation_history(url, id, title, last_visited_time, num_visits, locale) VALUES (?,?,?,?,?,?)",
+                params![
+                    format!("https://example.com/page{}", i),
+                    i as i64,
+                    format!("示例页面 {}", i),
+                    now - (i as i64 * 3600),
+                    (i % 7 + 1) as i64,
+                    if i % 2 == 0 { "en-us" } else { "zh-cn" }
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn with_conn<F, T>(f: F) -> Result<T, rusqlite::Error>
+where
+    F: FnOnce(&Connection) -> Result<T, rusqlite::Error>,
+{
+    get_or_create_connection()?;
+    vault::touch();
+
+    let guard = CONN.lock().unwrap();
+    match guard.as_ref() {
+        Some(conn) => f(conn),
+        None => Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISUSE),
+            Some("数据库连接未初始化".to_string()),
+        )),
+    }
+}
@@ -0,0 +1,522 @@
+use crate::exclusion::Blocklist;
+use crate::source::{self, SourceKind};
+use rusqlite::{params_from_iter, Connection};
+use std::collections::HashSet;
+
+// 删除条件，至少需指定一项；多项之间为“且”
+pub struct DeleteCriteria<'a> {
+    pub keyword: Option<&'a str>,
+    pub domain: Option<&'a str>,
+    pub lower: Option<i64>,
+    pub upper: Option<i64>,
+}
+
+impl DeleteCriteria<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.keyword.is_none()
+            && self.domain.is_none()
+            && self.lower.is_none()
+            && self.upper.is_none()
+    }
+
+    fn has_time_range(&self) -> bool {
+        self.lower.is_some() || self.upper.is_some()
+    }
+}
+
+pub struct DeletePlan {
+    kind: SourceKind,
+    // 需整体删除的页面（无剩余访问）
+    page_ids: Vec<i64>,
+    // 有访问被删除、需重算访问次数的页面
+    touched_ids: Vec<i64>,
+    visit_ids: Vec<i64>,
+    // 自有格式：被删除记录引用的产品实体
+    entity_ids: Vec<String>,
+    pub visit_count: i64,
+}
+
+impl DeletePlan {
+    pub fn page_count(&self) -> i64 {
+        self.page_ids.len() as i64
+    }
+}
+
+fn ids_in(ids: &[i64]) -> String {
+    vec!["?"; ids.len()].join(",")
+}
+
+// LIKE 中的通配符按字面匹配，避免关键字 % / _ 命中全部记录
+fn like_pattern(keyword: &str) -> String {
+    let mut escaped = String::with_capacity(keyword.len() + 2);
+    escaped.push('%');
+    for c in keyword.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped.push('%');
+    escaped
+}
+
+// 按条件选出候选页面：(页面 ID, 最后访问时间)
+fn candidate_pages(
+    conn: &Connection,
+    kind: SourceKind,
+    criteria: &DeleteCriteria,
+) -> rusqlite::Result<Vec<(i64, i64)>> {
+    let (table, id_col, time_col) = match kind {
+        SourceKind::NavigationHistory => ("navigation_history", "rowid", "last_visited_time"),
+        SourceKind::Chromium => ("urls", "id", "last_visit_time"),
+        SourceKind::Firefox => ("moz_places", "id", "last_visit_date"),
+    };
+    let mut sql = format!(
+        "SELECT {}, url, COALESCE({}, 0) FROM {} WHERE 1 = 1",
+        id_col, time_col, table
+    );
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(kw) = criteria.keyword {
+        sql.push_str(" AND (url LIKE ?1 ESCAPE '\\' OR title LIKE ?1 ESCAPE '\\')");
+        params.push(like_pattern(kw).into());
+    }
+
+    // 域名匹配（含子域名）与排除列表规则一致
+    let domain_matcher = criteria.domain.map(|d| {
+        Blocklist {
+            domains: vec![d.to_string()],
+            ..Default::default()
+        }
+        .normalized()
+    });
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(params_from_iter(params.iter()))?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(1)?;
+        if domain_matcher
+            .as_ref()
+            .is_some_and(|m| !m.matches(&url, None))
+        {
+            continue;
+        }
+        let raw_time: i64 = row.get(2)?;
+        let last_visited_time = match kind {
+            SourceKind::NavigationHistory => raw_time,
+            SourceKind::Chromium => source::chromium_time_to_unix(raw_time),
+            SourceKind::Firefox => source::firefox_time_to_unix(raw_time),
+        };
+        acc.push((row.get(0)?, last_visited_time));
+    }
+    Ok(acc)
+}
+
+pub fn plan(
+    conn: &Connection,
+    kind: SourceKind,
+    criteria: &DeleteCriteria,
+) -> rusqlite::Result<DeletePlan> {
+    let candidates = candidate_pages(conn, kind, criteria)?;
+    let mut plan = DeletePlan {
+        kind,
+        page_ids: Vec::new(),
+        touched_ids: Vec::new(),
+        visit_ids: Vec::new(),
+        entity_ids: Vec::new(),
+        visit_count: 0,
+    };
+
+    if kind == SourceKind::NavigationHistory {
+        // 自有格式没有逐次访问记录，按最后访问时间整行删除
+        let in_range = |ts: i64| {
+            criteria.lower.is_none_or(|l| ts >= l) && criteria.upper.is_none_or(|u| ts <= u)
+        };
+        plan.page_ids = candidates
+            .into_iter()
+            .filter(|(_, ts)| in_range(*ts))
+            .map(|(id, _)| id)
+            .collect();
+        for chunk in plan.page_ids.chunks(500) {
+            let sql = format!(
+                "SELECT COALESCE(SUM(num_visits), 0) FROM navigation_history WHERE rowid IN ({})",
+                ids_in(chunk)
+            );
+            plan.visit_count +=
+                conn.query_row(&sql, params_from_iter(chunk.iter()), |r| r.get::<_, i64>(0))?;
+            let sql = format!(
+                "SELECT DISTINCT product_entity_id FROM navigation_history
+                WHERE product_entity_id IS NOT NULL AND rowid IN ({})",
+                ids_in(chunk)
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let mut rows = stmt.query(params_from_iter(chunk.iter()))?;
+            while let Some(row) = rows.next()? {
+                plan.entity_ids.push(row.get(0)?);
+            }
+        }
+        plan.entity_ids.sort();
+        plan.entity_ids.dedup();
+        return Ok(plan);
+    }
+
+    let (visit_table, url_col, time_col, to_raw): (&str, &str, &str, fn(i64) -> i64) = match kind {
+        SourceKind::Chromium => ("visits", "url", "visit_time", source::unix_to_chromium_time),
+        _ => ("moz_historyvisits", "place_id", "visit_date", |ts| {
+            ts * 1_000_000
+        }),
+    };
+    let mut time_params: Vec<rusqlite::types::Value> = Vec::new();
+    let mut time_sql = String::new();
+    if let Some(lower) = criteria.lower {
+        time_sql.push_str(&format!(" AND {} >= ?", time_col));
+        time_params.push(to_raw(lower).into());
+    }
+    if let Some(upper) = criteria.upper {
+        time_sql.push_str(&format!(" AND {} <= ?", time_col));
+        time_params.push(to_raw(upper).into());
+    }
+
+    let candidate_ids: Vec<i64> = candidates.iter().map(|(id, _)| *id).collect();
+    let mut with_remaining: HashSet<i64> = HashSet::new();
+    for chunk in candidate_ids.chunks(500) {
+        let chunk_params = || {
+            chunk
+                .iter()
+                .map(|id| rusqlite::types::Value::from(*id))
+                .chain(time_params.iter().cloned())
+                .collect::<Vec<_>>()
+        };
+        let sql = format!(
+            "SELECT id, {} FROM {} WHERE {} IN ({}){}",
+            url_col,
+            visit_table,
+            url_col,
+            ids_in(chunk),
+            time_sql
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params_from_iter(chunk_params().iter()))?;
+        let mut touched: HashSet<i64> = HashSet::new();
+        while let Some(row) = rows.next()? {
+            plan.visit_ids.push(row.get(0)?);
+            touched.insert(row.get(1)?);
+        }
+
+        // 时间范围之外仍有访问的页面保留，仅重算统计
+        if criteria.has_time_range() {
+            let sql = format!(
+                "SELECT DISTINCT {} FROM {} WHERE {} IN ({}) AND NOT ({})",
+                url_col,
+                visit_table,
+                url_col,
+                ids_in(chunk),
+                time_sql.trim_start_matches(" AND ")
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let mut rows = stmt.query(params_from_iter(chunk_params().iter()))?;
+            while let Some(row) = rows.next()? {
+                with_remaining.insert(row.get(0)?);
+            }
+        }
+
+        for id in chunk {
+            // 没有任何访问的页面仅在未限定时间范围时删除
+            let fully_removed = if touched.contains(id) {
+                !with_remaining.contains(id)
+            } else {
+                !criteria.has_time_range()
+            };
+            if fully_removed {
+                plan.page_ids.push(*id);
+            } else if touched.contains(id) {
+                plan.touched_ids.push(*id);
+            }
+        }
+    }
+    plan.visit_count = plan.visit_ids.len() as i64;
+    Ok(plan)
+}
+
+fn delete_where_in(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    ids: &[i64],
+) -> rusqlite::Result<()> {
+    if !source::table_exists(conn, table)? {
+        return Ok(());
+    }
+    for chunk in ids.chunks(500) {
+        let sql = format!(
+            "DELETE FROM {} WHERE {} IN ({})",
+            table,
+            column,
+            ids_in(chunk)
+        );
+        conn.execute(&sql, params_from_iter(chunk.iter()))?;
+    }
+    Ok(())
+}
+
+pub fn execute(conn: &Connection, plan: &DeletePlan) -> rusqlite::Result<()> {
+    // 被释放的页面内容以零覆盖
+    conn.pragma_update(None, "secure_delete", "ON")?;
+    let tx = conn.unchecked_transaction()?;
+
+    match plan.kind {
+        SourceKind::NavigationHistory => {
+            delete_where_in(conn, "navigation_history", "rowid", &plan.page_ids)?;
+            // 仅清理本次删除后不再被引用的产品实体
+            if source::table_exists(conn, "product_entities")? {
+                for entity_id in &plan.entity_ids {
+                    conn.execute(
+                        "DELETE FROM product_entities WHERE product_entity_id = ?1
+                        AND NOT EXISTS (
+                            SELECT 1 FROM navigation_history WHERE product_entity_id = ?1
+                        )",
+                        [entity_id],
+                    )?;
+                }
+            }
+        }
+        SourceKind::Chromium => {
+            for (table, column) in [
+                ("visit_source", "id"),
+                ("content_annotations", "visit_id"),
+                ("context_annotations", "visit_id"),
+                ("clusters_and_visits", "visit_id"),
+                ("visits", "id"),
+            ] {
+                delete_where_in(conn, table, column, &plan.visit_ids)?;
+            }
+            if source::table_exists(conn, "segments")?
+                && source::table_exists(conn, "segment_usage")?
+            {
+                for chunk in plan.page_ids.chunks(500) {
+                    let sql = format!(
+                        "DELETE FROM segment_usage WHERE segment_id IN (
+                            SELECT id FROM segments WHERE url_id IN ({})
+                        )",
+                        ids_in(chunk)
+                    );
+                    conn.execute(&sql, params_from_iter(chunk.iter()))?;
+                }
+            }
+            for (table, column) in [
+                ("keyword_search_terms", "url_id"),
+                ("segments", "url_id"),
+                ("urls", "id"),
+            ] {
+                delete_where_in(conn, table, column, &plan.page_ids)?;
+            }
+            for chunk in plan.touched_ids.chunks(500) {
+                let sql = format!(
+                    "UPDATE urls SET
+                        visit_count = (SELECT COUNT(*) FROM visits WHERE visits.url = urls.id),
+                        last_visit_time = COALESCE(
+                            (SELECT MAX(visit_time) FROM visits WHERE visits.url = urls.id), 0)
+                    WHERE id IN ({})",
+                    ids_in(chunk)
+                );
+                conn.execute(&sql, params_from_iter(chunk.iter()))?;
+            }
+        }
+        SourceKind::Firefox => {
+            delete_where_in(conn, "moz_historyvisits", "id", &plan.visit_ids)?;
+            // 被书签引用的页面保留，只清空访问统计
+            let mut removable = Vec::new();
+            let mut bookmarked = Vec::new();
+            let has_bookmarks = source::table_exists(conn, "moz_bookmarks")?;
+            for id in &plan.page_ids {
+                let is_bookmarked = has_bookmarks
+                    && conn.query_row(
+                        "SELECT EXISTS (SELECT 1 FROM moz_bookmarks WHERE fk = ?1)",
+                        [id],
+                        |r| r.get::<_, bool>(0),
+                    )?;
+                if is_bookmarked {
+                    bookmarked.push(*id);
+                } else {
+                    removable.push(*id);
+                }
+            }
+            for (table, column) in [
+                ("moz_annos", "place_id"),
+                ("moz_inputhistory", "place_id"),
+                ("moz_places_metadata", "place_id"),
+                ("moz_places", "id"),
+            ] {
+                delete_where_in(conn, table, column, &removable)?;
+            }
+            let touched: Vec<i64> = plan
+                .touched_ids
+                .iter()
+                .chain(&bookmarked)
+                .copied()
+                .collect();
+            for chunk in touched.chunks(500) {
+                let sql = format!(
+                    "UPDATE moz_places SET
+                        visit_count = (SELECT COUNT(*) FROM moz_historyvisits v
+                            WHERE v.place_id = moz_places.id AND v.visit_type NOT IN (4, 8)),
+                        last_visit_date = (SELECT MAX(visit_date) FROM moz_historyvisits v
+                            WHERE v.place_id = moz_places.id)
+                    WHERE id IN ({})",
+                    ids_in(chunk)
+                );
+                conn.execute(&sql, params_from_iter(chunk.iter()))?;
+            }
+        }
+    }
+
+    tx.commit()?;
+
+    // 合并 WAL 并重建数据库文件，确保删除的数据不残留在磁盘上
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.execute_batch("VACUUM")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+    const T0: i64 = 1_770_000_000;
+
+    fn criteria(keyword: Option<&str>) -> DeleteCriteria<'_> {
+        DeleteCriteria {
+            keyword,
+            domain: None,
+            lower: None,
+            upper: None,
+        }
+    }
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |r| r.get(0)).unwrap()
+    }
+
+    // urls 1..=3 各有两次访问（T0 与 T0 + 10 天），每次访问都有 visit_source
+    fn chromium() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+                visit_count INTEGER, last_visit_time INTEGER);
+            CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER);
+            CREATE TABLE visit_source (id INTEGER PRIMARY KEY, source INTEGER);
+            INSERT INTO urls (id, url, title) VALUES
+                (1, 'https://a.com/100%', 'percent'),
+                (2, 'https://b.com/x_y', 'underscore'),
+                (3, 'https://c.com/plain', 'plain');",
+        )
+        .unwrap();
+        for url in 1..=3 {
+            for offset in [0, 10 * DAY] {
+                let time = source::unix_to_chromium_time(T0 + offset);
+                conn.execute(
+                    "INSERT INTO visits (url, visit_time) VALUES (?, ?)",
+                    [url, time],
+                )
+                .unwrap();
+                conn.execute(
+                    "INSERT INTO visit_source (id, source) VALUES (last_insert_rowid(), 0)",
+                    [],
+                )
+                .unwrap();
+            }
+        }
+        conn.execute(
+            "UPDATE urls SET visit_count = 2, last_visit_time = ?",
+            [source::unix_to_chromium_time(T0 + 10 * DAY)],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn keyword_wildcards_match_literally() {
+        let conn = chromium();
+        let plan_for = |kw| plan(&conn, SourceKind::Chromium, &criteria(Some(kw))).unwrap();
+        assert_eq!(plan_for("%").page_ids, vec![1]);
+        assert_eq!(plan_for("_").page_ids, vec![2]);
+        assert_eq!(plan_for("\\").page_count(), 0);
+        assert_eq!(plan_for("PLAIN").page_ids, vec![3]);
+    }
+
+    #[test]
+    fn time_range_plan_keeps_pages_with_remaining_visits() {
+        let conn = chromium();
+        let mut c = criteria(Some("b.com"));
+        c.upper = Some(T0 + DAY);
+        let plan = plan(&conn, SourceKind::Chromium, &c).unwrap();
+        assert_eq!(plan.visit_count, 1);
+        assert!(plan.page_ids.is_empty());
+        assert_eq!(plan.touched_ids, vec![2]);
+        // 仅生成计划不修改数据库
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM visits"), 6);
+    }
+
+    #[test]
+    fn chromium_delete_cascades_to_visits_and_visit_source() {
+        let conn = chromium();
+        let mut c = criteria(None);
+        c.domain = Some("a.com");
+        let p = plan(&conn, SourceKind::Chromium, &c).unwrap();
+        execute(&conn, &p).unwrap();
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM urls WHERE id = 1"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM visits WHERE url = 1"), 0);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM visit_source"), 4);
+
+        // 只删除时间范围内的访问时保留页面并重算访问次数
+        let mut c = criteria(Some("c.com"));
+        c.lower = Some(T0 + DAY);
+        let p = plan(&conn, SourceKind::Chromium, &c).unwrap();
+        execute(&conn, &p).unwrap();
+        assert_eq!(count(&conn, "SELECT visit_count FROM urls WHERE id = 3"), 1);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM visit_source"), 3);
+        assert_eq!(
+            count(&conn, "SELECT last_visit_time FROM urls WHERE id = 3"),
+            source::unix_to_chromium_time(T0)
+        );
+    }
+
+    #[test]
+    fn firefox_delete_cascades_and_keeps_bookmarked_places() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT,
+                visit_count INTEGER, last_visit_date INTEGER);
+            CREATE TABLE moz_historyvisits (id INTEGER PRIMARY KEY, place_id INTEGER,
+                visit_date INTEGER, visit_type INTEGER);
+            CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, fk INTEGER);
+            INSERT INTO moz_places VALUES
+                (1, 'https://a.com/', 'a', 1, 1), (2, 'https://a.com/kept', 'b', 1, 1),
+                (3, 'https://other.com/', 'c', 1, 1);
+            INSERT INTO moz_historyvisits (place_id, visit_date, visit_type)
+                VALUES (1, 1, 1), (2, 1, 1), (3, 1, 1);
+            INSERT INTO moz_bookmarks (fk) VALUES (2);",
+        )
+        .unwrap();
+        let mut c = criteria(None);
+        c.domain = Some("a.com");
+        let p = plan(&conn, SourceKind::Firefox, &c).unwrap();
+        assert_eq!(p.visit_count, 2);
+        execute(&conn, &p).unwrap();
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM moz_historyvisits"), 1);
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_places WHERE id = 1"),
+            0
+        );
+        assert_eq!(
+            count(&conn, "SELECT visit_count FROM moz_places WHERE id = 2"),
+            0
+        );
+        assert_eq!(
+            count(&conn, "SELECT COUNT(*) FROM moz_places WHERE id = 3"),
+            1
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::domain::{HistoryDetail, ProductEntity};
+use crate::source;
+use crate::visits;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+
+// 将扩展字段（JSON 文本 / BLOB）解析为结构化值，解析失败时保留原始内容
+fn decode_field(value: ValueRef<'_>) -> Option<Value> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(i) => Some(Value::from(i)),
+        ValueRef::Real(f) => Some(Value::from(f)),
+        ValueRef::Text(bytes) => decode_text(&String::from_utf8_lossy(bytes)),
+        ValueRef::Blob(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => decode_text(text),
+            Err(_) => Some(serde_json::json!({
+                "type": "blob",
+                "size": bytes.len(),
+                "hex": bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            })),
+        },
+    }
+}
+
+fn decode_text(text: &str) -> Option<Value> {
+    let text = text.trim_matches(char::from(0)).trim();
+    if text.is_empty() {
+        return None;
+    }
+    match serde_json::from_str::<Value>(text) {
+        // 部分字段是二次编码的 JSON 字符串，再尝试解析一层
+        Ok(Value::String(inner)) => match serde_json::from_str::<Value>(&inner) {
+            Ok(v @ (Value::Object(_) | Value::Array(_))) => Some(v),
+            _ => Some(Value::String(inner)),
+        },
+        Ok(v) => Some(v),
+        Err(_) => Some(Value::String(text.to_string())),
+    }
+}
+
+pub fn parse_keywords(raw: Option<String>) -> Vec<String> {
+    let raw = match raw {
+        Some(r) if !r.trim().is_empty() => r,
+        _ => return Vec::new(),
+    };
+    if let Ok(Value::Array(items)) = serde_json::from_str::<Value>(&raw) {
+        return items
+            .into_iter()
+            .filter_map(|v| match v {
+                Value::String(s) => Some(s),
+                Value::Null => None,
+                other => Some(other.to_string()),
+            })
+            .collect();
+    }
+    raw.split([',', ';', '|', '\n'])
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn load_product_entity(
+    conn: &Connection,
+    entity_id: &str,
+) -> rusqlite::Result<Option<ProductEntity>> {
+    if !source::table_exists(conn, "product_entities")? {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT product_entity_id, category, entity, search_keywords FROM product_entities WHERE product_entity_id = ?1",
+        params![entity_id],
+        |r| {
+            Ok(ProductEntity {
+                product_entity_id: r.get(0)?,
+                category: r.get(1)?,
+                entity: r.get(2)?,
+                search_keywords: parse_keywords(r.get(3)?),
+            })
+        },
+    )
+    .optional()
+}
+
+pub fn load_detail(conn: &Connection, url: &str) -> rusqlite::Result<Option<HistoryDetail>> {
+    let kind = source::detect(conn)?;
+
+    let detail = conn
+        .query_row(
+            "SELECT url, id, title, last_visited_time, num_visits, locale, product_entity_id,
+                metadata, titledata, urldata, page_profile
+            FROM navigation_history WHERE url = ?1 AND NOT excluded(url, title)",
+            params![url],
+            |r| {
+                Ok(HistoryDetail {
+                    url: r.get(0)?,
+                    id: r.get(1)?,
+                    title: r.get(2)?,
+                    last_visited_time: r.get(3)?,
+                    num_visits: r.get(4)?,
+                    locale: r.get(5)?,
+                    product_entity_id: r.get(6)?,
+                    product_entity: None,
+                    metadata: decode_field(r.get_ref(7)?),
+                    titledata: decode_field(r.get_ref(8)?),
+                    urldata: decode_field(r.get_ref(9)?),
+                    page_profile: decode_field(r.get_ref(10)?),
+                    source: kind,
+                    visits: Vec::new(),
+                })
+            },
+        )
+        .optional()?;
+
+    let mut detail = match detail {
+        Some(d) => d,
+        None => return Ok(None),
+    };
+
+    if let Some(entity_id) = detail.product_entity_id.clone() {
+        detail.product_entity = load_product_entity(conn, &entity_id)?;
+    }
+    if kind.has_visits() {
+        detail.visits = visits::list_visits_for_url(conn, kind, url)?;
+    }
+
+    Ok(Some(detail))
+}
@@ -0,0 +1,412 @@
+use crate::domain::{FirstSeenPage, FirstSeenSite};
+use crate::exclusion::{self, Blocklist};
+use crate::host::host_of;
+use crate::source::{self, SourceKind};
+use crate::vault::{self, VaultKey};
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// 每个页面的首次访问时间与截至 upper 的访问次数（首次访问以来）
+pub struct PageFirstSeen {
+    pub url: String,
+    pub title: Option<String>,
+    pub first_seen: i64,
+    pub visits: i64,
+}
+
+// 逐次访问型数据源取最早一次访问；自有格式没有逐次记录，以最早的最后访问时间近似
+pub fn load_pages(
+    conn: &Connection,
+    kind: SourceKind,
+    upper: Option<i64>,
+) -> rusqlite::Result<Vec<PageFirstSeen>> {
+    let (base_sql, time_col, to_raw): (&str, &str, fn(i64) -> i64) = match kind {
+        SourceKind::NavigationHistory => (
+            "SELECT url, MAX(title), MIN(last_visited_time), SUM(num_visits)
+            FROM navigation_history WHERE last_visited_time > 0 AND NOT excluded(url, title)",
+            "last_visited_time",
+            |ts| ts,
+        ),
+        SourceKind::Chromium => (
+            "SELECT u.url, u.title, MIN(v.visit_time), COUNT(*)
+            FROM visits v JOIN urls u ON u.id = v.url
+            WHERE (v.transition & 255) NOT IN (3, 4) AND NOT excluded(u.url, u.title)",
+            "v.visit_time",
+            source::unix_to_chromium_time,
+        ),
+        SourceKind::Firefox => (
+            "SELECT p.url, p.title, MIN(v.visit_date), COUNT(*)
+            FROM moz_historyvisits v JOIN moz_places p ON p.id = v.place_id
+            WHERE v.visit_type NOT IN (4, 8) AND NOT excluded(p.url, p.title)",
+            "v.visit_date",
+            |ts| ts * 1_000_000,
+        ),
+    };
+
+    let mut sql = base_sql.to_string();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(upper) = upper {
+        sql.push_str(&format!(" AND {} <= ?", time_col));
+        params.push(to_raw(upper).into());
+    }
+    sql.push_str(match kind {
+        SourceKind::NavigationHistory => " GROUP BY url",
+        SourceKind::Chromium => " GROUP BY u.id",
+        SourceKind::Firefox => " GROUP BY p.id",
+    });
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let raw_time: i64 = row.get(2)?;
+        let first_seen = match kind {
+            SourceKind::NavigationHistory => raw_time,
+            SourceKind::Chromium => source::chromium_time_to_unix(raw_time),
+            SourceKind::Firefox => source::firefox_time_to_unix(raw_time),
+        };
+        acc.push(PageFirstSeen {
+            url: row.get(0)?,
+            title: row.get(1)?,
+            first_seen,
+            visits: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+        });
+    }
+    Ok(acc)
+}
+
+// 应用目录中导入时留下的历史快照（history_*.db），不含当前数据库
+pub fn snapshot_paths(app_dir: &Path, current: Option<&str>) -> Vec<PathBuf> {
+    list_snapshots(app_dir, current, ".db")
+}
+
+// 已加密的快照（history_*.db.enc）
+pub fn encrypted_snapshot_paths(app_dir: &Path, current: Option<&str>) -> Vec<PathBuf> {
+    list_snapshots(
+        app_dir,
+        current,
+        &format!(".db{}", crate::vault::ENCRYPTED_SUFFIX),
+    )
+}
+
+fn list_snapshots(app_dir: &Path, current: Option<&str>, suffix: &str) -> Vec<PathBuf> {
+    let current = current.map(Path::new);
+    let Ok(entries) = std::fs::read_dir(app_dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name().is_some_and(|n| {
+                let name = n.to_string_lossy();
+                name.starts_with("history_") && name.ends_with(suffix)
+            }) && Some(p.as_path()) != current
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+// 旧快照中每个页面、每个站点的最早访问时间
+#[derive(Default)]
+pub struct SnapshotFirstSeen {
+    pub pages: HashMap<String, i64>,
+    // 站点的旧页面可能已全部过期，按站点单独记录，避免老站点被当作新站点
+    pub sites: HashMap<String, i64>,
+    pub used: usize,
+}
+
+fn keep_earliest(map: &mut HashMap<String, i64>, key: &str, ts: i64) {
+    let entry = map.entry(key.to_string()).or_insert(ts);
+    *entry = (*entry).min(ts);
+}
+
+// 单个快照的最早访问时间，按文件修改时间与排除列表缓存，快照未变化时不再重复读取
+struct CachedSnapshot {
+    modified: SystemTime,
+    blocklist: Blocklist,
+    pages: HashMap<String, i64>,
+    sites: HashMap<String, i64>,
+}
+
+static SNAPSHOT_CACHE: Lazy<Mutex<HashMap<PathBuf, CachedSnapshot>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 缓存中含有加密快照解密后的内容，锁定时一并清除
+pub fn clear_snapshot_cache() {
+    SNAPSHOT_CACHE.lock().unwrap().clear();
+}
+
+// 加密快照用当前会话密钥解密后在内存中打开，明文不落盘
+fn read_snapshot(
+    path: &Path,
+    key: Option<&VaultKey>,
+    blocklist: &Blocklist,
+) -> anyhow::Result<Vec<PageFirstSeen>> {
+    let conn = if vault::is_encrypted_file(path) {
+        let key = key.ok_or_else(|| anyhow!("快照已加密，请先解锁"))?;
+        let plaintext = vault::decrypt_with_key(key, &std::fs::read(path)?)?;
+        crate::db::open_from_bytes(&plaintext)?
+    } else {
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?
+    };
+    let kind = source::detect(&conn)?;
+    source::install_compat_views(&conn, kind)?;
+    exclusion::register(&conn, blocklist)?;
+    Ok(load_pages(&conn, kind, None)?)
+}
+
+fn load_snapshot(
+    path: &Path,
+    key: Option<&VaultKey>,
+    blocklist: &Blocklist,
+) -> anyhow::Result<CachedSnapshot> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    let mut snapshot = CachedSnapshot {
+        modified,
+        blocklist: blocklist.clone(),
+        pages: HashMap::new(),
+        sites: HashMap::new(),
+    };
+    for page in read_snapshot(path, key, blocklist)? {
+        if let Some(host) = host_of(&page.url) {
+            keep_earliest(&mut snapshot.sites, host, page.first_seen);
+        }
+        keep_earliest(&mut snapshot.pages, &page.url, page.first_seen);
+    }
+    Ok(snapshot)
+}
+
+// 浏览器会过期清理旧的访问记录（Chromium 默认保留 90 天），
+// 旧快照里仍保留的更早访问可以把首次访问时间往前推。
+// 加密快照需要传入当前会话密钥
+pub fn earliest_in_snapshots(
+    paths: &[PathBuf],
+    key: Option<&VaultKey>,
+    blocklist: &Blocklist,
+) -> SnapshotFirstSeen {
+    let blocklist = blocklist.normalized();
+    let mut cache = SNAPSHOT_CACHE.lock().unwrap();
+    cache.retain(|path, _| paths.contains(path));
+
+    let mut earliest = SnapshotFirstSeen::default();
+    for path in paths {
+        let fresh = cache.get(path).is_some_and(|cached| {
+            cached.blocklist == blocklist
+                && std::fs::metadata(path)
+                    .and_then(|m| m.modified())
+                    .is_ok_and(|modified| modified == cached.modified)
+        });
+        if !fresh {
+            match load_snapshot(path, key, &blocklist) {
+                Ok(snapshot) => {
+                    cache.insert(path.clone(), snapshot);
+                }
+                Err(e) => {
+                    cache.remove(path);
+                    eprintln!("读取历史快照失败 {:?}: {}", path, e);
+                    continue;
+                }
+            }
+        }
+        let snapshot = &cache[path];
+        earliest.used += 1;
+        for (site, ts) in &snapshot.sites {
+            keep_earliest(&mut earliest.sites, site, *ts);
+        }
+        for (url, ts) in &snapshot.pages {
+            keep_earliest(&mut earliest.pages, url, *ts);
+        }
+    }
+    earliest
+}
+
+pub fn apply_snapshots(pages: &mut [PageFirstSeen], earliest: &SnapshotFirstSeen) {
+    for page in pages {
+        if let Some(ts) = earliest.pages.get(&page.url) {
+            page.first_seen = page.first_seen.min(*ts);
+        }
+    }
+}
+
+// 首次访问落在 [lower, upper] 内的页面，按访问次数降序
+pub fn new_pages(pages: &[PageFirstSeen], lower: i64, upper: i64) -> Vec<FirstSeenPage> {
+    let mut items: Vec<FirstSeenPage> = pages
+        .iter()
+        .filter(|p| (lower..=upper).contains(&p.first_seen))
+        .map(|p| FirstSeenPage {
+            url: p.url.clone(),
+            title: p.title.clone(),
+            first_seen: p.first_seen,
+            visits: p.visits,
+        })
+        .collect();
+    items.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.url.cmp(&b.url)));
+    items
+}
+
+// 首次访问落在 [lower, upper] 内的站点，按访问次数降序；站点首次访问时间同时参考旧快照
+pub fn new_sites(
+    pages: &[PageFirstSeen],
+    earliest: &SnapshotFirstSeen,
+    lower: i64,
+    upper: i64,
+) -> Vec<FirstSeenSite> {
+    let mut sites: HashMap<&str, (i64, i64)> = HashMap::new();
+    for page in pages {
+        let Some(host) = host_of(&page.url) else {
+            continue;
+        };
+        let snapshot = earliest.sites.get(host).copied().unwrap_or(i64::MAX);
+        let entry = sites.entry(host).or_insert((snapshot, 0));
+        entry.0 = entry.0.min(page.first_seen);
+        entry.1 += page.visits;
+    }
+
+    let mut items: Vec<FirstSeenSite> = sites
+        .into_iter()
+        .filter(|(_, (first_seen, _))| (lower..=upper).contains(first_seen))
+        .map(|(site, (first_seen, visits))| FirstSeenSite {
+            site: site.to_string(),
+            first_seen,
+            visits,
+        })
+        .collect();
+    items.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.site.cmp(&b.site)));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str, first_seen: i64, visits: i64) -> PageFirstSeen {
+        PageFirstSeen {
+            url: url.to_string(),
+            title: None,
+            first_seen,
+            visits,
+        }
+    }
+
+    #[test]
+    fn load_pages_skips_subframes_and_respects_upper() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT);
+             CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER, transition INTEGER);
+             INSERT INTO urls VALUES (1, 'https://a.com/', 'A'), (2, 'https://ads.com/frame', NULL);",
+        )
+        .unwrap();
+        let t = source::unix_to_chromium_time;
+        conn.execute_batch(&format!(
+            "INSERT INTO visits VALUES (1, 1, {}, 1), (2, 1, {}, 0), (3, 1, {}, 0), (4, 2, {}, 3);",
+            t(200),
+            t(100),
+            t(900),
+            t(50)
+        ))
+        .unwrap();
+        exclusion::register(&conn, &Blocklist::default()).unwrap();
+
+        let pages = load_pages(&conn, SourceKind::Chromium, Some(500)).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            (pages[0].url.as_str(), pages[0].first_seen, pages[0].visits),
+            ("https://a.com/", 100, 2)
+        );
+    }
+
+    #[test]
+    fn snapshots_push_first_seen_back() {
+        let mut pages = vec![
+            page("https://old.com/new-page", 1_000, 4),
+            page("https://new.com/", 1_100, 2),
+            page("https://new.com/about", 1_200, 1),
+            page("https://kept.com/", 1_300, 3),
+        ];
+        let mut earliest = SnapshotFirstSeen::default();
+        // old.com 的旧页面已过期，只在快照中留有站点记录
+        earliest.sites.insert("old.com".into(), 10);
+        earliest.pages.insert("https://kept.com/".into(), 20);
+        earliest.sites.insert("kept.com".into(), 20);
+        apply_snapshots(&mut pages, &earliest);
+
+        let new_pages: Vec<String> = new_pages(&pages, 1_000, 2_000)
+            .into_iter()
+            .map(|p| p.url)
+            .collect();
+        assert_eq!(
+            new_pages,
+            vec![
+                "https://old.com/new-page",
+                "https://new.com/",
+                "https://new.com/about"
+            ]
+        );
+
+        let sites = new_sites(&pages, &earliest, 1_000, 2_000);
+        assert_eq!(sites.len(), 1);
+        assert_eq!(
+            (sites[0].site.as_str(), sites[0].first_seen, sites[0].visits),
+            ("new.com", 1_100, 3)
+        );
+        // 区间外的站点不计入
+        assert!(new_sites(&pages, &earliest, 1_150, 2_000).is_empty());
+    }
+
+    #[test]
+    fn encrypted_snapshots_need_the_key_and_are_cached_by_mtime() {
+        let dir = std::env::temp_dir().join(format!("bhb-discovery-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write_snapshot = |path: &Path, key: &VaultKey, first_seen: i64| {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(&format!(
+                "CREATE TABLE navigation_history (url TEXT, title TEXT, last_visited_time INTEGER, num_visits INTEGER);
+                 INSERT INTO navigation_history VALUES ('https://old.com/', 'Old', {}, 1);",
+                first_seen
+            ))
+            .unwrap();
+            let image = conn.serialize(rusqlite::DatabaseName::Main).unwrap();
+            vault::write_atomic(path, &vault::encrypt(key, &image).unwrap()).unwrap();
+        };
+        let key = VaultKey::derive("correct horse").unwrap();
+        let path = dir.join("history_1.db.enc");
+        write_snapshot(&path, &key, 100);
+        let paths = vec![path.clone()];
+
+        assert_eq!(
+            earliest_in_snapshots(&paths, None, &Blocklist::default()).used,
+            0
+        );
+        let earliest = earliest_in_snapshots(&paths, Some(&key), &Blocklist::default());
+        assert_eq!((earliest.used, earliest.sites["old.com"]), (1, 100));
+        // 命中缓存时无需密钥
+        assert_eq!(
+            earliest_in_snapshots(&paths, None, &Blocklist::default()).used,
+            1
+        );
+
+        // 文件修改时间变化后重新读取
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        write_snapshot(&path, &key, 50);
+        let earliest = earliest_in_snapshots(&paths, Some(&key), &Blocklist::default());
+        assert_eq!(earliest.pages["https://old.com/"], 50);
+
+        // 排除列表变化后重新读取
+        let blocklist = Blocklist {
+            domains: vec!["old.com".into()],
+            ..Blocklist::default()
+        };
+        let earliest = earliest_in_snapshots(&paths, Some(&key), &blocklist);
+        assert_eq!((earliest.used, earliest.sites.len()), (1, 0));
+
+        clear_snapshot_cache();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
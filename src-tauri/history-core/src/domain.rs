@@ -0,0 +1,404 @@
+use crate::source::SourceKind;
+use crate::transition::Transition;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryItem {
+    pub url: String,
+    pub title: Option<String>,
+    pub last_visited_time: i64, // epoch seconds
+    pub num_visits: i64,
+    pub duplicate_count: i64, // 归并模式下合并的记录数，否则为 1
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryListResponse {
+    pub items: Vec<HistoryItem>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductEntity {
+    pub product_entity_id: String,
+    pub category: Option<String>,
+    pub entity: Option<String>,
+    pub search_keywords: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VisitEntry {
+    pub visit_id: i64,
+    pub visit_time: i64, // epoch seconds
+    pub from_visit: Option<i64>,
+    pub referrer_url: Option<String>,
+    pub transition: Option<i64>, // 浏览器原始的跳转类型值
+    pub transition_type: Option<Transition>,
+    pub duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisitRecord {
+    pub visit_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub visit_time: i64, // epoch seconds
+    pub from_visit: Option<i64>,
+    pub transition: Option<i64>,
+    pub transition_type: Option<Transition>,
+    pub duration_secs: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryDetail {
+    pub url: String,
+    pub id: Option<i64>,
+    pub title: Option<String>,
+    pub last_visited_time: i64, // epoch seconds
+    pub num_visits: i64,
+    pub locale: Option<String>,
+    pub product_entity_id: Option<String>,
+    pub product_entity: Option<ProductEntity>,
+    // 扩展字段尽量解析为 JSON，无法解析时保留原始文本
+    pub metadata: Option<serde_json::Value>,
+    pub titledata: Option<serde_json::Value>,
+    pub urldata: Option<serde_json::Value>,
+    pub page_profile: Option<serde_json::Value>,
+    pub source: SourceKind,
+    pub visits: Vec<VisitEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    Title,
+    Host,
+    Keyword,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub text: String,
+    pub url: Option<String>,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteCount {
+    pub site: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BrowsingSession {
+    pub start_time: i64, // epoch seconds
+    pub end_time: i64,
+    pub duration_secs: i64,
+    pub page_count: i64,
+    pub distinct_pages: i64,
+    pub dominant_sites: Vec<SiteCount>,
+    pub visits: Vec<VisitRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<BrowsingSession>,
+    pub total: i64,
+    pub gap_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteTime {
+    pub site: String,
+    pub secs: f64,
+    pub visits: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryTime {
+    pub category: String,
+    pub secs: f64,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeBudgetPeriod {
+    pub period: String, // 2024-05-01 或 2024-W18
+    pub period_start: i64,
+    pub total_secs: f64,
+    pub sites: Vec<SiteTime>,
+    pub categories: Vec<CategoryTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimeBudgetReport {
+    pub granularity: String,
+    pub total_secs: f64,
+    pub periods: Vec<TimeBudgetPeriod>,
+    pub top_sites: Vec<SiteTime>,
+    pub top_categories: Vec<CategoryTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub label: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NavigationGraph {
+    pub level: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransitionCount {
+    pub transition: Transition,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SiteTransitions {
+    pub site: String,
+    pub total: i64,
+    pub counts: Vec<TransitionCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransitionStats {
+    pub total: i64,
+    pub totals: Vec<TransitionCount>,
+    pub sites: Vec<SiteTransitions>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchQueryItem {
+    pub engine: String,
+    pub query: String,
+    pub count: i64,
+    pub last_searched_time: i64, // epoch seconds
+    pub result_pages: i64,       // 对应的不同结果页 URL 数
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchQueryListResponse {
+    pub items: Vec<SearchQueryItem>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchTermItem {
+    pub term: String,
+    pub normalized_term: String,
+    pub searches: i64,     // 搜索（结果页访问）次数
+    pub result_pages: i64, // 不同结果页数量
+    pub last_searched_time: i64,
+    pub result_url: String, // 最近一次访问的结果页
+    pub result_title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchTermListResponse {
+    pub items: Vec<SearchTermItem>,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    InProgress,
+    Complete,
+    Cancelled,
+    Interrupted,
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownloadItem {
+    pub id: i64,
+    pub target_path: String,
+    pub file_name: String,
+    pub url: Option<String>,    // 最终下载地址（重定向链末端）
+    pub url_chain: Vec<String>, // 完整重定向链
+    pub referrer: Option<String>,
+    pub tab_url: Option<String>,
+    pub mime_type: Option<String>,
+    pub start_time: i64, // epoch seconds
+    pub end_time: Option<i64>,
+    pub received_bytes: i64,
+    pub total_bytes: Option<i64>,
+    pub state: DownloadState,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DownloadListResponse {
+    pub items: Vec<DownloadItem>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeletionReport {
+    pub dry_run: bool,
+    pub pages: i64,  // 整体删除的页面数
+    pub visits: i64, // 删除的访问次数
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LockStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+    pub auto_lock_minutes: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiServerStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub token_path: Option<String>, // 运行中才有令牌文件
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverviewStats {
+    pub total_visits: i64,
+    pub distinct_sites: i64,
+    pub top_entities: Vec<String>,
+    pub top_sites: Vec<SiteVisits>, // 与 top_entities 同序，附带访问次数
+    pub top_queries: Vec<SearchQueryItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteVisits {
+    pub site: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstSeenSite {
+    pub site: String,
+    pub first_seen: i64, // epoch seconds
+    pub visits: i64,     // 首次访问以来的访问次数
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstSeenPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub first_seen: i64,
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscoveryResponse {
+    pub sites: Vec<FirstSeenSite>,
+    pub pages: Vec<FirstSeenPage>,
+    pub total_sites: i64,
+    pub total_pages: i64,
+    pub snapshots_used: i64, // 参与推算首次访问时间的历史快照数
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryVisits {
+    pub category: String,
+    pub visits: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisitChange {
+    pub key: String, // 站点或类别
+    pub visits_a: i64,
+    pub visits_b: i64,
+    pub change: i64,             // B - A
+    pub change_pct: Option<f64>, // A 为 0 时为空
+    pub rank_a: Option<u32>,
+    pub rank_b: Option<u32>,
+    pub rank_change: Option<i64>, // 正数表示名次上升
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodComparison {
+    pub a_start: Option<i64>,
+    pub a_end: i64,
+    pub b_start: Option<i64>,
+    pub b_end: i64,
+    pub total_a: i64,
+    pub total_b: i64,
+    pub sites: Vec<VisitChange>,
+    pub categories: Vec<VisitChange>,
+    pub appeared: Vec<SiteVisits>,    // 仅在 B 中出现
+    pub disappeared: Vec<SiteVisits>, // 仅在 A 中出现
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendPoint {
+    pub day: String, // 本地日期 YYYY-MM-DD
+    pub visits: i64,
+    pub avg_7d: f64,
+    pub avg_28d: f64,
+    pub z_score: Option<f64>, // 相对此前 28 天；基线不足或无波动时为 None
+    pub anomaly: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SiteTrend {
+    pub site: String,
+    pub total_visits: i64,
+    pub anomaly_days: u32,
+    pub points: Vec<TrendPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrendReport {
+    pub start_day: String,
+    pub end_day: String,
+    pub threshold: f64,
+    pub sites: Vec<SiteTrend>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourVisits {
+    pub hour: u32, // 本地时间 0-23
+    pub visits: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestReport {
+    pub period_start: i64,
+    pub period_end: i64,
+    pub previous_start: i64,
+    pub previous_end: i64,
+    pub generated_at: i64,
+    pub current: OverviewStats,
+    pub previous: OverviewStats,
+    pub new_sites: Vec<FirstSeenSite>,
+    pub top_categories: Vec<CategoryVisits>,
+    pub hourly_visits: Vec<HourVisits>,
+}
+
+#[derive(thiserror::Error, Debug, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum AppError {
+    #[error("Database error: {0}")]
+    Db(String),
+    #[error("Invalid arguments: {0}")]
+    Invalid(String),
+    #[error("Internal: {0}")]
+    Internal(String),
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Db(e.to_string())
+    }
+}
+
+pub type AppResult<T> = Result<T, AppError>;
@@ -0,0 +1,153 @@
+use crate::domain::{DownloadItem, DownloadState};
+use crate::source::{self, SourceKind};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+impl DownloadState {
+    // Chromium downloads.state
+    fn from_chromium(state: i64) -> Self {
+        match state {
+            0 => DownloadState::InProgress,
+            1 => DownloadState::Complete,
+            2 => DownloadState::Cancelled,
+            3 | 4 => DownloadState::Interrupted,
+            _ => DownloadState::Unknown,
+        }
+    }
+
+    // Firefox downloads/metaData 中的 state
+    fn from_firefox(state: i64) -> Self {
+        match state {
+            1 => DownloadState::Complete,
+            2 | 6 | 8 => DownloadState::Interrupted,
+            3 => DownloadState::Cancelled,
+            4 => DownloadState::InProgress,
+            _ => DownloadState::Unknown,
+        }
+    }
+}
+
+fn file_name_of(path: &str) -> String {
+    path.rsplit(['/', '\\'])
+        .find(|s| !s.is_empty())
+        .unwrap_or(path)
+        .to_string()
+}
+
+fn load_chromium(conn: &Connection) -> rusqlite::Result<Vec<DownloadItem>> {
+    let mut chains: HashMap<i64, Vec<String>> = HashMap::new();
+    if source::table_exists(conn, "downloads_url_chains")? {
+        let mut stmt =
+            conn.prepare("SELECT id, url FROM downloads_url_chains ORDER BY id, chain_index")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            chains.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, target_path, start_time, end_time, received_bytes, total_bytes, state,
+            referrer, tab_url, mime_type
+        FROM downloads",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let target_path: String = row.get::<_, Option<String>>(1)?.unwrap_or_default();
+        let url_chain = chains.remove(&id).unwrap_or_default();
+        let end_time = source::chromium_time_to_unix(row.get::<_, Option<i64>>(3)?.unwrap_or(0));
+        let non_empty = |s: Option<String>| s.filter(|s| !s.is_empty());
+        acc.push(DownloadItem {
+            id,
+            file_name: file_name_of(&target_path),
+            target_path,
+            url: url_chain.last().cloned(),
+            url_chain,
+            referrer: non_empty(row.get(7)?),
+            tab_url: non_empty(row.get(8)?),
+            mime_type: non_empty(row.get(9)?),
+            start_time: source::chromium_time_to_unix(row.get::<_, Option<i64>>(2)?.unwrap_or(0)),
+            end_time: (end_time > 0).then_some(end_time),
+            received_bytes: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            total_bytes: row.get::<_, Option<i64>>(5)?.filter(|b| *b > 0),
+            state: DownloadState::from_chromium(row.get::<_, Option<i64>>(6)?.unwrap_or(-1)),
+        });
+    }
+    Ok(acc)
+}
+
+// Firefox 的下载记录保存在 moz_annos 注解中：目标文件 URI + JSON 元数据
+fn load_firefox(conn: &Connection) -> rusqlite::Result<Vec<DownloadItem>> {
+    if !source::table_exists(conn, "moz_annos")? {
+        return Ok(Vec::new());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.url, dest.content, dest.dateAdded, meta.content
+        FROM moz_annos dest
+        JOIN moz_anno_attributes da ON da.id = dest.anno_attribute_id
+        JOIN moz_places p ON p.id = dest.place_id
+        LEFT JOIN moz_annos meta ON meta.place_id = dest.place_id
+            AND meta.anno_attribute_id = (
+                SELECT id FROM moz_anno_attributes WHERE name = ?2
+            )
+        WHERE da.name = ?1",
+    )?;
+    let mut rows = stmt.query(params![
+        "downloads/destinationFileURI",
+        "downloads/metaData"
+    ])?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(1)?;
+        let dest: String = row.get::<_, Option<String>>(2)?.unwrap_or_default();
+        let target_path =
+            percent_encoding::percent_decode_str(dest.strip_prefix("file://").unwrap_or(&dest))
+                .decode_utf8_lossy()
+                .to_string();
+        let meta: serde_json::Value = row
+            .get::<_, Option<String>>(4)?
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_default();
+        let file_size = meta.get("fileSize").and_then(|v| v.as_i64());
+        let state = meta
+            .get("state")
+            .and_then(|v| v.as_i64())
+            .map(DownloadState::from_firefox)
+            .unwrap_or(DownloadState::Unknown);
+        // endTime 单位为毫秒
+        let end_time = meta
+            .get("endTime")
+            .and_then(|v| v.as_i64())
+            .map(|ms| ms / 1000);
+
+        acc.push(DownloadItem {
+            id: row.get(0)?,
+            file_name: file_name_of(&target_path),
+            target_path,
+            url: Some(url.clone()),
+            url_chain: vec![url],
+            referrer: None,
+            tab_url: None,
+            mime_type: None,
+            start_time: source::firefox_time_to_unix(row.get::<_, Option<i64>>(3)?.unwrap_or(0)),
+            end_time,
+            received_bytes: if state == DownloadState::Complete {
+                file_size.unwrap_or(0)
+            } else {
+                0
+            },
+            total_bytes: file_size,
+            state,
+        });
+    }
+    Ok(acc)
+}
+
+pub fn load_downloads(conn: &Connection, kind: SourceKind) -> rusqlite::Result<Vec<DownloadItem>> {
+    match kind {
+        SourceKind::Chromium if source::table_exists(conn, "downloads")? => load_chromium(conn),
+        SourceKind::Firefox => load_firefox(conn),
+        _ => Ok(Vec::new()),
+    }
+}
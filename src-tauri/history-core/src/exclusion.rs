@@ -0,0 +1,197 @@
+use crate::host::host_of;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+// 排除列表：命中的记录在 SQL 层即被过滤，不会出现在任何输出、统计或导出中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Blocklist {
+    // 域名，同时匹配其子域名
+    #[serde(default)]
+    pub domains: Vec<String>,
+    // URL 通配符，* 匹配任意字符，? 匹配单个字符，不区分大小写
+    #[serde(default)]
+    pub url_globs: Vec<String>,
+    // 标题关键字，不区分大小写
+    #[serde(default)]
+    pub title_keywords: Vec<String>,
+}
+
+impl Blocklist {
+    // 统一为小写、去掉空项与域名前缀的 *. / .
+    pub fn normalized(&self) -> Self {
+        let clean = |items: &[String]| -> Vec<String> {
+            items
+                .iter()
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        Self {
+            domains: clean(&self.domains)
+                .into_iter()
+                .map(|d| {
+                    d.trim_start_matches("*.")
+                        .trim_start_matches('.')
+                        .to_string()
+                })
+                .filter(|d| !d.is_empty())
+                .collect(),
+            url_globs: clean(&self.url_globs),
+            title_keywords: clean(&self.title_keywords),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.url_globs.is_empty() && self.title_keywords.is_empty()
+    }
+
+    // 调用方需保证已 normalized
+    pub fn matches(&self, url: &str, title: Option<&str>) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        let url = url.to_lowercase();
+        if let Some(host) = host_of(&url) {
+            // 去掉端口
+            let host = host.rsplit_once(':').map_or(host, |(h, _)| h);
+            if self.domains.iter().any(|d| {
+                host == d
+                    || host
+                        .strip_suffix(d.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            }) {
+                return true;
+            }
+        }
+        if self.url_globs.iter().any(|g| glob_match(g, &url)) {
+            return true;
+        }
+        if let Some(title) = title.filter(|_| !self.title_keywords.is_empty()) {
+            let title = title.to_lowercase();
+            if self
+                .title_keywords
+                .iter()
+                .any(|k| title.contains(k.as_str()))
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    // 最近一个 * 的位置及其匹配起点，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+// 注册 SQL 函数 excluded(url, title)；排除列表变更后需重新注册
+pub fn register(conn: &Connection, blocklist: &Blocklist) -> rusqlite::Result<()> {
+    let blocklist = blocklist.normalized();
+    conn.create_scalar_function(
+        "excluded",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let url = ctx.get::<Option<String>>(0)?.unwrap_or_default();
+            let title = ctx.get::<Option<String>>(1)?;
+            Ok(blocklist.matches(&url, title.as_deref()))
+        },
+    )
+}
+
+// 供各查询拼接的过滤条件
+pub fn condition(url_col: &str, title_col: &str) -> String {
+    format!("NOT excluded({}, {})", url_col, title_col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocklist(domains: &[&str], url_globs: &[&str], title_keywords: &[&str]) -> Blocklist {
+        let owned = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Blocklist {
+            domains: owned(domains),
+            url_globs: owned(url_globs),
+            title_keywords: owned(title_keywords),
+        }
+        .normalized()
+    }
+
+    #[test]
+    fn globs_are_anchored_to_the_whole_url() {
+        let b = blocklist(&[], &["https://a.com/private*", "*/secret?.html"], &[]);
+        assert!(b.matches("https://a.com/private/x", None));
+        assert!(b.matches("HTTPS://A.COM/Private", None));
+        assert!(!b.matches("https://b.com/?next=https://a.com/private", None));
+        assert!(b.matches("https://c.com/docs/secret1.html", None));
+        assert!(!b.matches("https://c.com/docs/secret1.html?x=1", None));
+        assert!(!b.matches("https://c.com/docs/secret.html", None));
+    }
+
+    #[test]
+    fn domains_match_subdomains_but_not_lookalikes() {
+        let b = blocklist(&["*.Example.com", "  "], &[], &[]);
+        assert_eq!(b.domains, vec!["example.com"]);
+        assert!(b.matches("https://example.com/", None));
+        assert!(b.matches("https://mail.EXAMPLE.com:8443/inbox", None));
+        assert!(!b.matches("https://notexample.com/", None));
+        assert!(!b.matches("https://example.com.evil.net/", None));
+        assert!(!b.matches("https://other.net/?u=example.com", None));
+    }
+
+    #[test]
+    fn title_keywords_fold_case() {
+        let b = blocklist(&[], &[], &["Bank Statement"]);
+        assert!(b.matches("https://a.com/", Some("Your BANK statement is ready")));
+        assert!(!b.matches("https://a.com/", Some("Bank holidays")));
+        assert!(!b.matches("https://a.com/", None));
+        assert!(!Blocklist::default().matches("https://a.com/", Some("bank statement")));
+    }
+
+    #[test]
+    fn registered_function_filters_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        register(
+            &conn,
+            &Blocklist {
+                domains: vec!["Example.com".into()],
+                ..Blocklist::default()
+            },
+        )
+        .unwrap();
+        let hidden: bool = conn
+            .query_row(
+                "SELECT excluded('https://www.example.com/', NULL)",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert!(hidden);
+        let shown: bool = conn
+            .query_row("SELECT excluded(NULL, 'example.com')", [], |r| r.get(0))
+            .unwrap();
+        assert!(!shown);
+    }
+}
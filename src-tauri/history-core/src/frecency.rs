@@ -0,0 +1,29 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+// 访问频次 × 时间衰减：每经过一个半衰期，权重减半
+pub const DEFAULT_HALF_LIFE_DAYS: f64 = 30.0;
+
+pub fn score(num_visits: i64, last_visited_time: i64, now: i64, half_life_days: f64) -> f64 {
+    let visits = num_visits.max(0) as f64;
+    let half_life_secs = half_life_days.max(0.01) * 86400.0;
+    let age_secs = (now - last_visited_time).max(0) as f64;
+    visits * 0.5f64.powf(age_secs / half_life_secs)
+}
+
+// 注册 SQL 函数 frecency(num_visits, last_visited_time, now, half_life_days)，
+// 使排序可以与过滤、分页一起在 SQL 中完成
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "frecency",
+        4,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let num_visits = ctx.get::<Option<i64>>(0)?.unwrap_or(0);
+            let last_visited_time = ctx.get::<Option<i64>>(1)?.unwrap_or(0);
+            let now: i64 = ctx.get(2)?;
+            let half_life_days: f64 = ctx.get(3)?;
+            Ok(score(num_visits, last_visited_time, now, half_life_days))
+        },
+    )
+}
@@ -0,0 +1,31 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+// 从 URL 中提取站点（host[:port]），非 http/https 链接返回 None
+pub fn host_of(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    // 去掉 user:pass@ 前缀
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+// 注册 SQL 函数 url_host(url)，便于按站点聚合；非 http/https 链接返回 NULL
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "url_host",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let url = ctx.get::<Option<String>>(0)?.unwrap_or_default();
+            Ok(host_of(&url).map(str::to_string))
+        },
+    )
+}
@@ -0,0 +1,261 @@
+use crate::config::AppConfig;
+use crate::db::with_conn;
+use crate::domain::{AppError, AppResult, HistoryItem};
+use crate::frecency;
+use crate::host::host_of;
+use crate::redact;
+use crate::suggest::tokens;
+use std::io::{BufRead, Write};
+
+// 启动器模式（rofi / dmenu / ulauncher 等）：启动时把历史载入内存，
+// 之后逐行读取查询，每个查询输出若干行 `标题\tURL`，以空行结束
+pub const DEFAULT_LIMIT: usize = 20;
+
+struct Entry {
+    title: String,
+    url: String,
+    // 小写的 标题 + URL，用于子串与子序列匹配
+    haystack: String,
+    // 标题、站点、URL 路径中的词，用于词前缀匹配
+    words: Vec<String>,
+    num_visits: i64,
+    last_visited_time: i64,
+}
+
+pub struct LauncherIndex {
+    entries: Vec<Entry>,
+    half_life_days: f64,
+}
+
+// 词前缀 > 子串 > 子序列，逐个查询词取最佳命中，任一词未命中则整条不匹配
+fn term_weight(entry: &Entry, term: &str) -> Option<f64> {
+    if entry.words.iter().any(|w| w.starts_with(term)) {
+        Some(1.0)
+    } else if entry.haystack.contains(term) {
+        Some(0.6)
+    } else if is_subsequence(term, &entry.haystack) {
+        Some(0.1)
+    } else {
+        None
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut rest = haystack.chars();
+    needle.chars().all(|c| rest.any(|h| h == c))
+}
+
+// 制表符与换行会破坏逐行协议
+fn clean(text: &str) -> String {
+    text.replace(['\t', '\r', '\n'], " ")
+}
+
+impl LauncherIndex {
+    pub fn build() -> AppResult<Self> {
+        let config = AppConfig::load().map_err(|e| AppError::Internal(e.to_string()))?;
+        // 按规范化 URL 归并，保留最近一次访问的 URL 与标题
+        let items = with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT url, title, MAX(last_visited_time), SUM(num_visits), COUNT(*)
+                FROM navigation_history
+                WHERE NOT excluded(url, title)
+                GROUP BY canonical_url(url)",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(HistoryItem {
+                    url: row.get(0)?,
+                    title: row.get(1)?,
+                    last_visited_time: row.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    num_visits: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                    duplicate_count: row.get(4)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })?;
+
+        let entries = redact::apply(items)
+            .into_iter()
+            .map(|item| {
+                let title = item
+                    .title
+                    .filter(|t| !t.trim().is_empty())
+                    .unwrap_or_else(|| item.url.clone());
+                let lower_title = title.to_lowercase();
+                let lower_url = item.url.to_lowercase();
+                let mut words: Vec<String> = tokens(&lower_title)
+                    .chain(tokens(&lower_url))
+                    .map(str::to_string)
+                    .collect();
+                if let Some(host) = host_of(&lower_url) {
+                    words.push(host.strip_prefix("www.").unwrap_or(host).to_string());
+                }
+                words.sort();
+                words.dedup();
+                Entry {
+                    haystack: format!("{} {}", lower_title, lower_url),
+                    title: clean(&title),
+                    url: clean(&item.url),
+                    words,
+                    num_visits: item.num_visits,
+                    last_visited_time: item.last_visited_time,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            entries,
+            half_life_days: config.frecency_half_life_days,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // 空查询返回 frecency 最高的条目
+    pub fn query(&self, q: &str, limit: usize) -> Vec<(&str, &str)> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let lower = q.trim().to_lowercase();
+        let terms: Vec<&str> = lower.split_whitespace().collect();
+        let now = chrono::Utc::now().timestamp();
+
+        let mut ranked: Vec<(f64, &Entry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let mut weight = 1.0;
+                for term in &terms {
+                    weight *= term_weight(entry, term)?;
+                }
+                // 标题整体以查询开头时加权
+                if !lower.is_empty() && entry.haystack.starts_with(&lower) {
+                    weight *= 2.0;
+                }
+                let base = frecency::score(
+                    entry.num_visits,
+                    entry.last_visited_time,
+                    now,
+                    self.half_life_days,
+                );
+                // 从未访问的条目仍按匹配程度排序
+                Some(((base + 0.01) * weight, entry))
+            })
+            .collect();
+        // 只需前 limit 个：先部分选择再对这部分排序，同分按 URL 排序保证结果稳定
+        let by_score = |a: &(f64, &Entry), b: &(f64, &Entry)| {
+            b.0.total_cmp(&a.0).then_with(|| a.1.url.cmp(&b.1.url))
+        };
+        if ranked.len() > limit {
+            ranked.select_nth_unstable_by(limit - 1, by_score);
+            ranked.truncate(limit);
+        }
+        ranked.sort_by(by_score);
+
+        ranked
+            .into_iter()
+            .map(|(_, e)| (e.title.as_str(), e.url.as_str()))
+            .collect()
+    }
+
+    // 每读到一行查询就输出结果并以空行结束，输入结束时返回
+    pub fn serve_lines<R: BufRead, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+        limit: usize,
+    ) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            for (title, url) in self.query(&line, limit) {
+                writeln!(writer, "{}\t{}", title, url)?;
+            }
+            writeln!(writer)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // 在 Unix socket 上提供同样的逐行协议，每个连接独立处理
+    #[cfg(unix)]
+    pub fn serve_socket(
+        self: std::sync::Arc<Self>,
+        path: &std::path::Path,
+        limit: usize,
+    ) -> std::io::Result<()> {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        use std::os::unix::net::UnixListener;
+
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        // 先在仅本人可访问（0700）的临时目录里创建 socket 并收紧权限，再移动到目标路径，
+        // 避免 bind 后、chmod 前按默认 umask 被其他用户连接
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let staging = path.with_file_name(format!(".{}.{}", name, std::process::id()));
+        std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("socket");
+        let bound = UnixListener::bind(&staged).and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        let _ = std::fs::remove_file(&staged);
+        let _ = std::fs::remove_dir(&staging);
+        let listener = bound?;
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let index = std::sync::Arc::clone(&self);
+            std::thread::spawn(move || {
+                let reader = match stream.try_clone() {
+                    Ok(s) => std::io::BufReader::new(s),
+                    Err(_) => return,
+                };
+                let _ = index.serve_lines(reader, stream, limit);
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, url: &str, num_visits: i64) -> Entry {
+        let haystack = format!("{} {}", title, url).to_lowercase();
+        Entry {
+            title: title.to_string(),
+            url: url.to_string(),
+            words: tokens(&haystack).map(|w| w.to_string()).collect(),
+            haystack,
+            num_visits,
+            last_visited_time: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    #[test]
+    fn query_keeps_the_best_matches_in_order() {
+        let index = LauncherIndex {
+            entries: (1..=50)
+                .map(|i| entry(&format!("Rust {}", i), &format!("https://r{}.com/", i), i))
+                .chain([entry("Other", "https://other.com/", 1000)])
+                .collect(),
+            half_life_days: 30.0,
+        };
+        let urls: Vec<&str> = index.query("rust", 3).into_iter().map(|(_, u)| u).collect();
+        assert_eq!(
+            urls,
+            vec!["https://r50.com/", "https://r49.com/", "https://r48.com/"]
+        );
+        // 空查询按 frecency 排序
+        assert_eq!(index.query("", 1)[0].1, "https://other.com/");
+        assert_eq!(index.query("rust", 100).len(), 50);
+        assert!(index.query("rust", 0).is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+// history-core：数据源、查询、统计与配置的纯 Rust 接口，桌面应用与命令行工具共用
+pub mod api;
+pub mod canonical;
+pub mod categories;
+pub mod compare;
+pub mod config;
+pub mod db;
+pub mod deletion;
+pub mod detail;
+pub mod discovery;
+pub mod domain;
+pub mod downloads;
+pub mod exclusion;
+pub mod frecency;
+pub mod host;
+pub mod launcher;
+pub mod localtime;
+pub mod navgraph;
+pub mod redact;
+pub mod report;
+pub mod rollup;
+pub mod search_queries;
+pub mod search_terms;
+pub mod server;
+pub mod sessions;
+pub mod source;
+pub mod suggest;
+pub mod timespent;
+pub mod transition;
+pub mod trends;
+pub mod vault;
+pub mod visits;
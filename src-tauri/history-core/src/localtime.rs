@@ -0,0 +1,46 @@
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+
+// 按本地时区分日：汇总表、时间预算与趋势共用，保证同一时刻落在同一天
+
+// 本地日期零点的时间戳；零点落在夏令时跳变的空档中时取当天第一个存在的整点
+pub fn local_midnight(day: NaiveDate) -> i64 {
+    let midnight = day.and_time(chrono::NaiveTime::MIN);
+    (0..=2)
+        .find_map(|h| {
+            Local
+                .from_local_datetime(&(midnight + Duration::hours(h)))
+                .earliest()
+        })
+        .map(|dt| dt.timestamp())
+        .unwrap_or_else(|| midnight.and_utc().timestamp())
+}
+
+// 时间戳所在的本地日期
+pub fn local_day(ts: i64) -> NaiveDate {
+    Local
+        .timestamp_opt(ts, 0)
+        .earliest()
+        .map(|dt| dt.date_naive())
+        .unwrap_or_else(|| {
+            chrono::DateTime::from_timestamp(ts, 0)
+                .map(|dt| dt.date_naive())
+                .unwrap_or_default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 任意时区下零点都属于当天，零点前一秒属于前一天（在 TZ=America/Sao_Paulo 下覆盖零点跳变）
+    #[test]
+    fn midnight_and_day_agree() {
+        let first = NaiveDate::from_ymd_opt(2018, 1, 1).unwrap();
+        for offset in 0..3 * 366 {
+            let day = first + Duration::days(offset);
+            let midnight = local_midnight(day);
+            assert_eq!(local_day(midnight), day);
+            assert_eq!(local_day(midnight - 1), day - Duration::days(1));
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::domain::{GraphEdge, GraphNode, NavigationGraph, VisitRecord};
+use crate::host::host_of;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphLevel {
+    Page,
+    Site,
+}
+
+impl GraphLevel {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value {
+            Some("site") | None => Some(GraphLevel::Site),
+            Some("page") => Some(GraphLevel::Page),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GraphLevel::Page => "page",
+            GraphLevel::Site => "site",
+        }
+    }
+
+    fn key(self, url: &str) -> String {
+        match self {
+            GraphLevel::Page => url.to_string(),
+            GraphLevel::Site => host_of(url).unwrap_or(url).to_string(),
+        }
+    }
+}
+
+// 由访问记录的 from_visit 构建导航图。referrer_urls 提供时间范围外来源访问的 URL。
+// 自环（同一页面/站点内跳转）不计入边；节点按访问量截取前 max_nodes 个。
+pub fn build_graph(
+    visits: &[VisitRecord],
+    referrer_urls: &HashMap<i64, String>,
+    level: GraphLevel,
+    min_weight: i64,
+    max_nodes: usize,
+) -> NavigationGraph {
+    let visit_urls: HashMap<i64, &str> = visits
+        .iter()
+        .map(|v| (v.visit_id, v.url.as_str()))
+        .collect();
+
+    let mut nodes: HashMap<String, (String, i64)> = HashMap::new();
+    let mut edges: HashMap<(String, String), i64> = HashMap::new();
+
+    for v in visits {
+        let target = level.key(&v.url);
+        let node = nodes.entry(target.clone()).or_insert_with(|| {
+            let label = match level {
+                GraphLevel::Page => v.title.clone().filter(|t| !t.is_empty()),
+                GraphLevel::Site => None,
+            };
+            (label.unwrap_or_else(|| target.clone()), 0)
+        });
+        node.1 += 1;
+
+        let from_url = v.from_visit.and_then(|id| {
+            visit_urls
+                .get(&id)
+                .copied()
+                .or_else(|| referrer_urls.get(&id).map(|u| u.as_str()))
+        });
+        if let Some(from_url) = from_url {
+            let source = level.key(from_url);
+            if source != target {
+                *edges.entry((source, target)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // 来源节点可能不在时间范围内，补充为零访问节点
+    for (source, _) in edges.keys() {
+        nodes
+            .entry(source.clone())
+            .or_insert_with(|| (source.clone(), 0));
+    }
+
+    let mut edges: Vec<GraphEdge> = edges
+        .into_iter()
+        .filter(|(_, weight)| *weight >= min_weight)
+        .map(|((source, target), weight)| GraphEdge {
+            source,
+            target,
+            weight,
+        })
+        .collect();
+
+    // 只保留参与连边的节点（孤立节点不论访问量都不显示），按 访问量 + 连边权重 取前 max_nodes 个
+    let mut degree: HashMap<&str, i64> = HashMap::new();
+    for e in &edges {
+        *degree.entry(e.source.as_str()).or_insert(0) += e.weight;
+        *degree.entry(e.target.as_str()).or_insert(0) += e.weight;
+    }
+    let mut ranked: Vec<(&String, &(String, i64))> = nodes
+        .iter()
+        .filter(|(id, _)| degree.contains_key(id.as_str()))
+        .collect();
+    ranked.sort_by(|a, b| {
+        let wa = a.1 .1 + degree.get(a.0.as_str()).copied().unwrap_or(0);
+        let wb = b.1 .1 + degree.get(b.0.as_str()).copied().unwrap_or(0);
+        wb.cmp(&wa).then_with(|| a.0.cmp(b.0))
+    });
+    ranked.truncate(max_nodes);
+
+    let kept: HashSet<&str> = ranked.iter().map(|(id, _)| id.as_str()).collect();
+    let node_list: Vec<GraphNode> = ranked
+        .iter()
+        .map(|(id, (label, visits))| GraphNode {
+            id: (*id).clone(),
+            label: label.clone(),
+            visits: *visits,
+        })
+        .collect();
+    edges.retain(|e| kept.contains(e.source.as_str()) && kept.contains(e.target.as_str()));
+    edges.sort_by_key(|e| std::cmp::Reverse(e.weight));
+
+    NavigationGraph {
+        level: level.as_str().to_string(),
+        nodes: node_list,
+        edges,
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn to_graphml(graph: &NavigationGraph) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    out.push_str("  <key id=\"visits\" for=\"node\" attr.name=\"visits\" attr.type=\"long\"/>\n");
+    out.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"long\"/>\n");
+    out.push_str(&format!(
+        "  <graph id=\"navigation\" edgedefault=\"directed\" level=\"{}\">\n",
+        graph.level
+    ));
+    for n in &graph.nodes {
+        out.push_str(&format!(
+            "    <node id=\"{}\">\n      <data key=\"label\">{}</data>\n      <data key=\"visits\">{}</data>\n    </node>\n",
+            xml_escape(&n.id),
+            xml_escape(&n.label),
+            n.visits
+        ));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "    <edge source=\"{}\" target=\"{}\">\n      <data key=\"weight\">{}</data>\n    </edge>\n",
+            xml_escape(&e.source),
+            xml_escape(&e.target),
+            e.weight
+        ));
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+pub fn to_dot(graph: &NavigationGraph) -> String {
+    let mut out = String::from("digraph navigation {\n");
+    for n in &graph.nodes {
+        out.push_str(&format!(
+            "  \"{}\" [label=\"{}\", visits={}];\n",
+            dot_escape(&n.id),
+            dot_escape(&n.label),
+            n.visits
+        ));
+    }
+    for e in &graph.edges {
+        out.push_str(&format!(
+            "  \"{}\" -> \"{}\" [weight={}, label=\"{}\"];\n",
+            dot_escape(&e.source),
+            dot_escape(&e.target),
+            e.weight,
+            e.weight
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,372 @@
+use crate::domain::{CategoryVisits, DigestReport, HourVisits};
+use crate::timespent::UNCATEGORIZED;
+use chrono::{Local, TimeZone};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// 周报 / 月报：汇总指定时间范围与上一个等长时间范围，渲染为自包含的 Markdown 或 HTML
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+// 按本地时间的小时统计的访问次数，固定 24 项
+pub fn hourly_visits(counts: &[i64; 24]) -> Vec<HourVisits> {
+    counts
+        .iter()
+        .enumerate()
+        .map(|(hour, visits)| HourVisits {
+            hour: hour as u32,
+            visits: *visits,
+        })
+        .collect()
+}
+
+// 未分类的访问不参与排名
+pub fn top_categories(counts: &HashMap<String, i64>, limit: usize) -> Vec<CategoryVisits> {
+    let mut items: Vec<CategoryVisits> = counts
+        .iter()
+        .filter(|(category, _)| category.as_str() != UNCATEGORIZED)
+        .map(|(category, visits)| CategoryVisits {
+            category: category.clone(),
+            visits: *visits,
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    items.truncate(limit);
+    items
+}
+
+fn format_time(ts: i64) -> String {
+    Local
+        .timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn change(current: i64, previous: i64) -> String {
+    if previous == 0 {
+        return if current == 0 {
+            "—".into()
+        } else {
+            "新增".into()
+        };
+    }
+    let pct = (current - previous) as f64 / previous as f64 * 100.0;
+    format!("{:+.1}%", pct)
+}
+
+// 访问最多的 3 个小时
+fn busiest_hours(report: &DigestReport) -> Vec<&HourVisits> {
+    let mut hours: Vec<&HourVisits> = report
+        .hourly_visits
+        .iter()
+        .filter(|h| h.visits > 0)
+        .collect();
+    hours.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.hour.cmp(&b.hour)));
+    hours.truncate(3);
+    hours
+}
+
+fn md_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+pub fn to_markdown(report: &DigestReport) -> String {
+    let mut out = String::new();
+    let cur = &report.current;
+    let prev = &report.previous;
+
+    let _ = writeln!(out, "# 浏览摘要");
+    let _ = writeln!(
+        out,
+        "\n{} ~ {}（对比 {} ~ {}）\n",
+        format_time(report.period_start),
+        format_time(report.period_end),
+        format_time(report.previous_start),
+        format_time(report.previous_end)
+    );
+
+    let _ = writeln!(out, "## 总览\n");
+    let _ = writeln!(out, "| 指标 | 本期 | 上期 | 变化 |\n|---|---:|---:|---:|");
+    let _ = writeln!(
+        out,
+        "| 访问次数 | {} | {} | {} |",
+        cur.total_visits,
+        prev.total_visits,
+        change(cur.total_visits, prev.total_visits)
+    );
+    let _ = writeln!(
+        out,
+        "| 站点数 | {} | {} | {} |",
+        cur.distinct_sites,
+        prev.distinct_sites,
+        change(cur.distinct_sites, prev.distinct_sites)
+    );
+
+    let _ = writeln!(out, "\n## Top 站点\n");
+    if cur.top_sites.is_empty() {
+        let _ = writeln!(out, "无访问记录");
+    } else {
+        let _ = writeln!(out, "| # | 站点 | 访问次数 |\n|---:|---|---:|");
+        for (i, site) in cur.top_sites.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} |",
+                i + 1,
+                md_cell(&site.site),
+                site.visits
+            );
+        }
+    }
+
+    let _ = writeln!(out, "\n## 新发现的站点\n");
+    if report.new_sites.is_empty() {
+        let _ = writeln!(out, "本期没有首次访问的站点");
+    } else {
+        let _ = writeln!(out, "| 站点 | 首次访问 | 访问次数 |\n|---|---|---:|");
+        for site in &report.new_sites {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} |",
+                md_cell(&site.site),
+                format_time(site.first_seen),
+                site.visits
+            );
+        }
+    }
+
+    if !report.top_categories.is_empty() {
+        let _ = writeln!(out, "\n## Top 类别\n");
+        let _ = writeln!(out, "| 类别 | 访问次数 |\n|---|---:|");
+        for c in &report.top_categories {
+            let _ = writeln!(out, "| {} | {} |", md_cell(&c.category), c.visits);
+        }
+    }
+
+    let _ = writeln!(out, "\n## 最活跃时段\n");
+    let busiest = busiest_hours(report);
+    if busiest.is_empty() {
+        let _ = writeln!(out, "无访问记录");
+    }
+    for h in busiest {
+        let _ = writeln!(out, "- {:02}:00–{:02}:59：{} 次", h.hour, h.hour, h.visits);
+    }
+
+    if !cur.top_queries.is_empty() {
+        let _ = writeln!(out, "\n## 热门搜索\n");
+        let _ = writeln!(out, "| 搜索词 | 引擎 | 次数 |\n|---|---|---:|");
+        for q in &cur.top_queries {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} |",
+                md_cell(&q.query),
+                md_cell(&q.engine),
+                q.count
+            );
+        }
+    }
+
+    let _ = writeln!(out, "\n---\n生成于 {}", format_time(report.generated_at));
+    out
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,'Segoe UI','Microsoft YaHei',sans-serif;max-width:860px;margin:2em auto;padding:0 1em;color:#222}
+h1{margin-bottom:.2em}.period{color:#666;margin-top:0}
+table{border-collapse:collapse;width:100%;margin:.5em 0 1.5em}th,td{border-bottom:1px solid #e5e5e5;padding:.4em .6em;text-align:left}
+td.num,th.num{text-align:right}.up{color:#1a7f37}.down{color:#cf222e}
+.hours{display:flex;align-items:flex-end;gap:2px;height:120px;margin:.5em 0 .2em}
+.hours div{flex:1;background:#4f7cff;min-height:1px}.hour-labels{display:flex;justify-content:space-between;color:#888;font-size:.8em}
+footer{color:#888;font-size:.85em;margin-top:2em}";
+
+fn change_cell(current: i64, previous: i64) -> String {
+    let class = match current.cmp(&previous) {
+        std::cmp::Ordering::Greater => "num up",
+        std::cmp::Ordering::Less => "num down",
+        std::cmp::Ordering::Equal => "num",
+    };
+    format!("<td class=\"{}\">{}</td>", class, change(current, previous))
+}
+
+pub fn to_html(report: &DigestReport) -> String {
+    let mut out = String::new();
+    let cur = &report.current;
+    let prev = &report.previous;
+
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>浏览摘要 {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        html_escape(&format_time(report.period_start)),
+        HTML_STYLE
+    );
+    let _ = writeln!(out, "<h1>浏览摘要</h1>");
+    let _ = writeln!(
+        out,
+        "<p class=\"period\">{} ~ {}（对比 {} ~ {}）</p>",
+        format_time(report.period_start),
+        format_time(report.period_end),
+        format_time(report.previous_start),
+        format_time(report.previous_end)
+    );
+
+    let _ = writeln!(out, "<h2>总览</h2>\n<table><tr><th>指标</th><th class=\"num\">本期</th><th class=\"num\">上期</th><th class=\"num\">变化</th></tr>");
+    for (label, c, p) in [
+        ("访问次数", cur.total_visits, prev.total_visits),
+        ("站点数", cur.distinct_sites, prev.distinct_sites),
+    ] {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>{}</tr>",
+            label,
+            c,
+            p,
+            change_cell(c, p)
+        );
+    }
+    let _ = writeln!(out, "</table>");
+
+    let _ = writeln!(out, "<h2>Top 站点</h2>");
+    if cur.top_sites.is_empty() {
+        let _ = writeln!(out, "<p>无访问记录</p>");
+    } else {
+        let _ = writeln!(
+            out,
+            "<table><tr><th class=\"num\">#</th><th>站点</th><th class=\"num\">访问次数</th></tr>"
+        );
+        for (i, site) in cur.top_sites.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "<tr><td class=\"num\">{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                i + 1,
+                html_escape(&site.site),
+                site.visits
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    let _ = writeln!(out, "<h2>新发现的站点</h2>");
+    if report.new_sites.is_empty() {
+        let _ = writeln!(out, "<p>本期没有首次访问的站点</p>");
+    } else {
+        let _ = writeln!(
+            out,
+            "<table><tr><th>站点</th><th>首次访问</th><th class=\"num\">访问次数</th></tr>"
+        );
+        for site in &report.new_sites {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                html_escape(&site.site),
+                format_time(site.first_seen),
+                site.visits
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    if !report.top_categories.is_empty() {
+        let _ = writeln!(
+            out,
+            "<h2>Top 类别</h2>\n<table><tr><th>类别</th><th class=\"num\">访问次数</th></tr>"
+        );
+        for c in &report.top_categories {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"num\">{}</td></tr>",
+                html_escape(&c.category),
+                c.visits
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    let _ = writeln!(out, "<h2>最活跃时段</h2>");
+    let max = report
+        .hourly_visits
+        .iter()
+        .map(|h| h.visits)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let _ = write!(out, "<div class=\"hours\">");
+    for h in &report.hourly_visits {
+        let _ = write!(
+            out,
+            "<div style=\"height:{:.0}%\" title=\"{:02}:00 {} 次\"></div>",
+            h.visits as f64 / max as f64 * 100.0,
+            h.hour,
+            h.visits
+        );
+    }
+    let _ = writeln!(
+        out,
+        "</div>\n<div class=\"hour-labels\"><span>0 时</span><span>6 时</span><span>12 时</span><span>18 时</span><span>23 时</span></div>"
+    );
+    let busiest: Vec<String> = busiest_hours(report)
+        .iter()
+        .map(|h| format!("{:02}:00（{} 次）", h.hour, h.visits))
+        .collect();
+    if !busiest.is_empty() {
+        let _ = writeln!(out, "<p>访问最多：{}</p>", busiest.join("、"));
+    }
+
+    if !cur.top_queries.is_empty() {
+        let _ = writeln!(out, "<h2>热门搜索</h2>\n<table><tr><th>搜索词</th><th>引擎</th><th class=\"num\">次数</th></tr>");
+        for q in &cur.top_queries {
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>",
+                html_escape(&q.query),
+                html_escape(&q.engine),
+                q.count
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    let _ = writeln!(
+        out,
+        "<footer>生成于 {}</footer>\n</body>\n</html>",
+        format_time(report.generated_at)
+    );
+    out
+}
+
+pub fn render(report: &DigestReport, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Markdown => to_markdown(report),
+        ReportFormat::Html => to_html(report),
+    }
+}
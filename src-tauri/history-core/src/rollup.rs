@@ -0,0 +1,1081 @@
+use crate::categories;
+use crate::config::AppConfig;
+use crate::localtime::{local_day, local_midnight};
+use crate::source::{self, SourceKind, WEBKIT_EPOCH_OFFSET_SECS};
+use crate::timespent::UNCATEGORIZED;
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+// 按日预聚合表（总量 / 日 × 站点 / 日 × 类别 / 日 × 小时，含访问次数与估算停留时长），
+// 存放在应用目录的独立数据库中并以 rollup 附加到连接，不写入浏览器数据库；加密数据库解锁后附加内存库，汇总数据不落盘。
+// 每个数据源记录已汇总到的时间 last_computed 以及此前数据的行数 / 访问数，
+// 统计前检查：此前部分未变则从 last_computed 所在日重新汇总（该日最后一次访问的停留时长取决于之后的访问），
+// 否则（删除、浏览器过期清理、排除列表、分类规则或空闲阈值变更）整体重建
+const ROLLUP_FILE: &str = "rollups/rollups.db";
+// 表结构变更时递增，旧版本的汇总表直接丢弃重建
+const SCHEMA_VERSION: i64 = 2;
+const TABLES: [&str; 5] = [
+    "rollup_sources",
+    "rollup_daily",
+    "rollup_daily_sites",
+    "rollup_daily_categories",
+    "rollup_daily_hours",
+];
+// 无法根据后续访问推断停留时长时（会话最后一页），按固定停留时间估算
+const DEFAULT_DWELL_SECS: f64 = 60.0;
+
+fn rollup_path() -> Result<String, String> {
+    let dir = AppConfig::get_app_dir().map_err(|e| e.to_string())?;
+    let path = dir.join(ROLLUP_FILE);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+// 汇总库文件不可用（目录无法创建、文件损坏或被占用）时退回内存库，不影响打开数据库
+pub fn attach(conn: &Connection) -> rusqlite::Result<()> {
+    if conn.path().is_some_and(|p| !p.is_empty()) {
+        let attached =
+            rollup_path().and_then(|path| attach_to(conn, &path).map_err(|e| e.to_string()));
+        match attached {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                eprintln!("无法使用汇总数据库，改用内存库: {}", e);
+                conn.execute_batch("DETACH DATABASE rollup").ok();
+            }
+        }
+    }
+    attach_to(conn, ":memory:")
+}
+
+fn attach_to(conn: &Connection, target: &str) -> rusqlite::Result<()> {
+    conn.execute("ATTACH DATABASE ?1 AS rollup", [target])?;
+    let version: i64 = conn.query_row("PRAGMA rollup.user_version", [], |r| r.get(0))?;
+    if version != SCHEMA_VERSION {
+        for table in TABLES {
+            conn.execute_batch(&format!("DROP TABLE IF EXISTS rollup.{}", table))?;
+        }
+        conn.execute_batch(&format!("PRAGMA rollup.user_version = {}", SCHEMA_VERSION))?;
+    }
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS rollup.rollup_sources (
+            source TEXT PRIMARY KEY,
+            rules_hash TEXT NOT NULL,
+            last_computed INTEGER NOT NULL,
+            covered_rows INTEGER NOT NULL,
+            covered_visits INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS rollup.rollup_daily (
+            source TEXT NOT NULL,
+            day TEXT NOT NULL,
+            total_visits INTEGER NOT NULL,
+            distinct_sites INTEGER NOT NULL DEFAULT 0,
+            secs REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (source, day)
+        );
+        CREATE TABLE IF NOT EXISTS rollup.rollup_daily_sites (
+            source TEXT NOT NULL,
+            day TEXT NOT NULL,
+            site TEXT NOT NULL,
+            visits INTEGER NOT NULL,
+            secs REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (source, day, site)
+        );
+        CREATE TABLE IF NOT EXISTS rollup.rollup_daily_categories (
+            source TEXT NOT NULL,
+            day TEXT NOT NULL,
+            category TEXT NOT NULL,
+            visits INTEGER NOT NULL,
+            secs REAL NOT NULL DEFAULT 0,
+            PRIMARY KEY (source, day, category)
+        );
+        CREATE TABLE IF NOT EXISTS rollup.rollup_daily_hours (
+            source TEXT NOT NULL,
+            day TEXT NOT NULL,
+            hour INTEGER NOT NULL,
+            visits INTEGER NOT NULL,
+            PRIMARY KEY (source, day, hour)
+        );
+        "#,
+    )
+}
+
+// 各数据源的逐次访问：原始时间列（数据源自身单位）、Unix 秒表达式、URL / 标题列与权重。
+// 自有格式每个 URL 只有最后访问时间，全部访问次数计入该日
+struct Events {
+    table: &'static str,
+    alias: &'static str,
+    time_col: &'static str,
+    // 每行代表的访问次数列，None 表示每行一次访问
+    weight: Option<&'static str>,
+    from_sql: String,
+    secs_expr: String,
+    url_col: &'static str,
+    category_expr: String,
+    // 浏览器记录的停留时长（微秒），没有则为 NULL
+    duration_expr: &'static str,
+    to_raw: fn(i64) -> i64,
+    from_raw: fn(i64) -> i64,
+}
+
+impl Events {
+    fn of(conn: &Connection, kind: SourceKind) -> rusqlite::Result<Self> {
+        Ok(match kind {
+            SourceKind::NavigationHistory => {
+                let has_entities = source::table_exists(conn, "product_entities")?;
+                let join = if has_entities {
+                    "LEFT JOIN product_entities pe ON pe.product_entity_id = n.product_entity_id"
+                } else {
+                    ""
+                };
+                Self {
+                    table: "navigation_history",
+                    alias: "n",
+                    time_col: "last_visited_time",
+                    weight: Some("num_visits"),
+                    from_sql: format!(
+                        "FROM navigation_history n {} WHERE n.last_visited_time > 0 AND NOT excluded(n.url, n.title)",
+                        join
+                    ),
+                    secs_expr: "n.last_visited_time".to_string(),
+                    url_col: "n.url",
+                    category_expr: categories::sql_expr(
+                        has_entities.then_some("pe.category"),
+                        "n.url",
+                        "n.title",
+                    ),
+                    duration_expr: "NULL",
+                    to_raw: |ts| ts,
+                    from_raw: |ts| ts,
+                }
+            }
+            SourceKind::Chromium => Self {
+                table: "visits",
+                alias: "v",
+                time_col: "visit_time",
+                weight: None,
+                from_sql: "FROM visits v JOIN urls u ON u.id = v.url
+                    WHERE (v.transition & 255) NOT IN (3, 4) AND NOT excluded(u.url, u.title)"
+                    .to_string(),
+                secs_expr: format!("v.visit_time / 1000000 - {}", WEBKIT_EPOCH_OFFSET_SECS),
+                url_col: "u.url",
+                category_expr: categories::sql_expr(None, "u.url", "u.title"),
+                duration_expr: "v.visit_duration",
+                to_raw: source::unix_to_chromium_time,
+                from_raw: source::chromium_time_to_unix,
+            },
+            SourceKind::Firefox => Self {
+                table: "moz_historyvisits",
+                alias: "v",
+                time_col: "visit_date",
+                weight: None,
+                from_sql: "FROM moz_historyvisits v JOIN moz_places p ON p.id = v.place_id
+                    WHERE v.visit_type NOT IN (4, 8) AND NOT excluded(p.url, p.title)"
+                    .to_string(),
+                secs_expr: "v.visit_date / 1000000".to_string(),
+                url_col: "p.url",
+                category_expr: categories::sql_expr(None, "p.url", "p.title"),
+                duration_expr: "NULL",
+                to_raw: |ts| ts * 1_000_000,
+                from_raw: source::firefox_time_to_unix,
+            },
+        })
+    }
+
+    // 带表别名的原始时间列
+    fn qualified_time_col(&self) -> String {
+        format!("{}.{}", self.alias, self.time_col)
+    }
+
+    fn weight_expr(&self) -> String {
+        match self.weight {
+            Some(col) => format!("{}.{}", self.alias, col),
+            None => "1".to_string(),
+        }
+    }
+
+    // Unix 秒闭区间换算为原始时间闭区间
+    fn raw_range(&self, lower: Option<i64>, upper: Option<i64>) -> (Option<i64>, Option<i64>) {
+        (
+            lower.map(self.to_raw),
+            upper.map(|u| (self.to_raw)(u + 1) - 1),
+        )
+    }
+}
+
+// 访问次数与估算停留秒数
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Tally {
+    pub visits: i64,
+    pub secs: f64,
+}
+
+impl Tally {
+    fn add(&mut self, visits: i64, secs: f64) {
+        self.visits += visits;
+        self.secs += secs;
+    }
+}
+
+#[derive(Default)]
+struct Aggregate {
+    days: HashMap<String, Tally>,
+    sites: HashMap<(String, String), Tally>,
+    categories: HashMap<(String, String), Tally>,
+    hours: HashMap<(String, u32), i64>,
+}
+
+fn idle_gap_secs(config: &AppConfig) -> i64 {
+    config.session_gap_minutes as i64 * 60
+}
+
+// 原始时间闭区间内的访问按 日 / 日 × 站点 / 日 × 类别 / 日 × 小时 聚合。
+// 停留时长优先使用浏览器记录的时长，否则取到下一次访问的间隔，两者都以空闲阈值封顶，
+// 超过阈值视为离开；区间内最后一次访问按固定时长估算
+fn aggregate(
+    conn: &Connection,
+    events: &Events,
+    lower_raw: Option<i64>,
+    upper_raw: Option<i64>,
+    idle_gap_secs: i64,
+) -> rusqlite::Result<Aggregate> {
+    let time_col = events.qualified_time_col();
+    let mut inner = format!(
+        "SELECT {secs} AS secs, LEAD({secs}) OVER (ORDER BY {time}) AS next_secs,
+            {url} AS url, {category} AS category_name, {weight} AS weight, {duration} AS duration
+        {from}",
+        secs = events.secs_expr,
+        time = time_col,
+        url = events.url_col,
+        category = events.category_expr,
+        weight = events.weight_expr(),
+        duration = events.duration_expr,
+        from = events.from_sql
+    );
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(lower) = lower_raw {
+        inner.push_str(&format!(" AND {} >= ?", time_col));
+        params.push(lower.into());
+    }
+    if let Some(upper) = upper_raw {
+        inner.push_str(&format!(" AND {} <= ?", time_col));
+        params.push(upper.into());
+    }
+    let cap = idle_gap_secs.max(1);
+    let sql = format!(
+        "SELECT date(secs, 'unixepoch', 'localtime') AS day,
+            CAST(strftime('%H', secs, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+            url_host(url) AS site, category_name, SUM(weight),
+            SUM(CASE
+                WHEN duration > 0 THEN MIN(duration / 1000000.0, {cap})
+                WHEN next_secs - secs <= {cap} THEN next_secs - secs
+                ELSE MIN({dwell}, {cap})
+            END)
+        FROM ({inner})
+        GROUP BY day, hour, site, category_name",
+        cap = cap,
+        dwell = DEFAULT_DWELL_SECS,
+        inner = inner
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    let mut acc = Aggregate::default();
+    while let Some(row) = rows.next()? {
+        let Some(day) = row.get::<_, Option<String>>(0)? else {
+            continue;
+        };
+        let visits = row.get::<_, Option<i64>>(4)?.unwrap_or(0);
+        let secs = row.get::<_, Option<f64>>(5)?.unwrap_or(0.0);
+        acc.days.entry(day.clone()).or_default().add(visits, secs);
+        if let Some(hour) = row.get::<_, Option<u32>>(1)? {
+            *acc.hours.entry((day.clone(), hour)).or_default() += visits;
+        }
+        if let Some(site) = row.get::<_, Option<String>>(2)? {
+            acc.sites
+                .entry((day.clone(), site))
+                .or_default()
+                .add(visits, secs);
+        }
+        let category = row
+            .get::<_, Option<String>>(3)?
+            .unwrap_or_else(|| UNCATEGORIZED.to_string());
+        acc.categories
+            .entry((day, category))
+            .or_default()
+            .add(visits, secs);
+    }
+    Ok(acc)
+}
+
+// 截至 last_computed 的原始行数与访问数，不经过排除列表，只用来判断已汇总部分是否变化
+fn fingerprint(conn: &Connection, events: &Events, upto_raw: i64) -> rusqlite::Result<(i64, i64)> {
+    let weight = match events.weight {
+        Some(col) => format!("TOTAL({})", col),
+        None => "COUNT(*)".to_string(),
+    };
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*), CAST({} AS INTEGER) FROM {} WHERE {} > 0 AND {} <= ?",
+            weight, events.table, events.time_col, events.time_col
+        ),
+        [upto_raw],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )
+}
+
+fn source_key(conn: &Connection) -> String {
+    match conn.path().filter(|p| !p.is_empty()) {
+        Some(path) => std::fs::canonicalize(path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string()),
+        // 解锁后的加密数据库以配置中的文件路径区分
+        None => AppConfig::load()
+            .ok()
+            .and_then(|c| c.db_path)
+            .unwrap_or_else(|| ":memory:".to_string()),
+    }
+}
+
+// 排除列表、分类规则与空闲阈值会影响汇总结果，变更后需要重建
+fn rules_hash(config: &AppConfig) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    serde_json::to_string(&config.blocklist)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    serde_json::to_string(&config.category_rules)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    idle_gap_secs(config).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+// 检查当前数据源并增量更新汇总表
+pub fn refresh(conn: &Connection) -> rusqlite::Result<()> {
+    let config = AppConfig::load().unwrap_or_default();
+    refresh_with_rules(conn, &rules_hash(&config), idle_gap_secs(&config))
+}
+
+fn refresh_with_rules(conn: &Connection, rules: &str, idle_gap_secs: i64) -> rusqlite::Result<()> {
+    let kind = source::detect(conn)?;
+    let events = Events::of(conn, kind)?;
+    let key = source_key(conn);
+
+    let latest: i64 = conn
+        .query_row(
+            &format!("SELECT MAX({}) FROM {}", events.time_col, events.table),
+            [],
+            |r| r.get::<_, Option<i64>>(0),
+        )?
+        .unwrap_or(0);
+    let state: Option<(String, i64, i64, i64)> = conn
+        .query_row(
+            "SELECT rules_hash, last_computed, covered_rows, covered_visits
+            FROM rollup.rollup_sources WHERE source = ?",
+            [&key],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?)),
+        )
+        .optional()?;
+
+    // 增量起点：已汇总部分未变化时从 last_computed 所在日的零点开始，否则整体重建
+    let incremental_from = match state {
+        Some((hash, last, rows, visits))
+            if hash == rules && fingerprint(conn, &events, last)? == (rows, visits) =>
+        {
+            if latest <= last {
+                return Ok(());
+            }
+            Some(local_day((events.from_raw)(last)))
+        }
+        _ => None,
+    };
+
+    let tx = conn.unchecked_transaction()?;
+    match incremental_from {
+        Some(day) => {
+            let day = day.format("%Y-%m-%d").to_string();
+            for table in &TABLES[1..] {
+                tx.execute(
+                    &format!("DELETE FROM rollup.{} WHERE source = ? AND day >= ?", table),
+                    params![key, day],
+                )?;
+            }
+        }
+        None => {
+            delete_source(&tx, &key)?;
+            prune_missing_sources(&tx)?;
+        }
+    }
+
+    let lower_raw = incremental_from.map(|day| (events.to_raw)(local_midnight(day)));
+    let acc = aggregate(&tx, &events, lower_raw, Some(latest), idle_gap_secs)?;
+    {
+        let mut daily = tx.prepare(
+            "INSERT INTO rollup.rollup_daily(source, day, total_visits, secs) VALUES (?, ?, ?, ?)",
+        )?;
+        for (day, tally) in &acc.days {
+            daily.execute(params![key, day, tally.visits, tally.secs])?;
+        }
+        let mut sites = tx.prepare(
+            "INSERT INTO rollup.rollup_daily_sites(source, day, site, visits, secs) VALUES (?, ?, ?, ?, ?)",
+        )?;
+        for ((day, site), tally) in &acc.sites {
+            sites.execute(params![key, day, site, tally.visits, tally.secs])?;
+        }
+        let mut cats = tx.prepare(
+            "INSERT INTO rollup.rollup_daily_categories(source, day, category, visits, secs)
+            VALUES (?, ?, ?, ?, ?)",
+        )?;
+        for ((day, category), tally) in &acc.categories {
+            cats.execute(params![key, day, category, tally.visits, tally.secs])?;
+        }
+        let mut hours = tx.prepare(
+            "INSERT INTO rollup.rollup_daily_hours(source, day, hour, visits) VALUES (?, ?, ?, ?)",
+        )?;
+        for ((day, hour), visits) in &acc.hours {
+            hours.execute(params![key, day, hour, visits])?;
+        }
+    }
+    // 站点数不可累加，按日从 日 × 站点 表重新计数
+    if let Some(first_day) = acc.days.keys().min() {
+        tx.execute(
+            "UPDATE rollup.rollup_daily SET distinct_sites = (
+                SELECT COUNT(*) FROM rollup.rollup_daily_sites s
+                WHERE s.source = rollup_daily.source AND s.day = rollup_daily.day)
+            WHERE source = ? AND day >= ?",
+            params![key, first_day],
+        )?;
+    }
+
+    let (rows, visits) = fingerprint(&tx, &events, latest)?;
+    tx.execute(
+        "INSERT OR REPLACE INTO rollup.rollup_sources
+        (source, rules_hash, last_computed, covered_rows, covered_visits, updated_at)
+        VALUES (?, ?, ?, ?, ?, ?)",
+        params![
+            key,
+            rules,
+            latest,
+            rows,
+            visits,
+            chrono::Utc::now().timestamp()
+        ],
+    )?;
+    tx.commit()
+}
+
+// 导入会不断产生新的数据库副本，已不存在的数据库文件对应的汇总一并清除
+fn prune_missing_sources(conn: &Connection) -> rusqlite::Result<()> {
+    let sources: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT source FROM rollup.rollup_sources")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    for source in sources {
+        if source == ":memory:" || Path::new(&source).exists() {
+            continue;
+        }
+        delete_source(conn, &source)?;
+    }
+    Ok(())
+}
+
+fn delete_source(conn: &Connection, source: &str) -> rusqlite::Result<()> {
+    for table in TABLES {
+        conn.execute(
+            &format!("DELETE FROM rollup.{} WHERE source = ?", table),
+            [source],
+        )?;
+    }
+    Ok(())
+}
+
+// 数据库加密前调用：清除当前数据源的汇总并 VACUUM，使明文汇总不再留在磁盘上
+pub fn forget_current(conn: &Connection) -> rusqlite::Result<()> {
+    delete_source(conn, &source_key(conn))?;
+    conn.execute_batch("VACUUM rollup")
+}
+
+// 时间范围拆分为整日部分（读汇总表）与首尾不满一天的部分（直接查询数据源）
+struct Split {
+    full_days: Option<(Option<String>, Option<String>)>,
+    partial: Vec<(i64, i64)>,
+}
+
+fn split(lower: Option<i64>, upper: Option<i64>) -> Split {
+    let fmt = |d: NaiveDate| d.format("%Y-%m-%d").to_string();
+    // 第一个整日的起点、最后一个整日的次日起点
+    let full_start = lower.map(|l| {
+        let day = local_day(l);
+        if local_midnight(day) == l {
+            l
+        } else {
+            local_midnight(day + Duration::days(1))
+        }
+    });
+    let full_end = upper.map(|u| {
+        let day = local_day(u);
+        let next = local_midnight(day + Duration::days(1));
+        if next == u + 1 {
+            next
+        } else {
+            local_midnight(day)
+        }
+    });
+
+    if let (Some(l), Some(u), Some(fs), Some(fe)) = (lower, upper, full_start, full_end) {
+        if fs >= fe {
+            return Split {
+                full_days: None,
+                partial: if l <= u { vec![(l, u)] } else { Vec::new() },
+            };
+        }
+    }
+
+    let mut partial = Vec::new();
+    if let (Some(l), Some(fs)) = (lower, full_start) {
+        if l < fs {
+            partial.push((l, fs - 1));
+        }
+    }
+    if let (Some(u), Some(fe)) = (upper, full_end) {
+        if fe <= u {
+            partial.push((fe, u));
+        }
+    }
+    Split {
+        full_days: Some((
+            full_start.map(|ts| fmt(local_day(ts))),
+            full_end.map(|ts| fmt(local_day(ts) - Duration::days(1))),
+        )),
+        partial,
+    }
+}
+
+fn day_filter(
+    from: &Option<String>,
+    to: &Option<String>,
+    params: &mut Vec<rusqlite::types::Value>,
+) -> String {
+    let mut sql = String::new();
+    if let Some(from) = from {
+        sql.push_str(" AND day >= ?");
+        params.push(from.clone().into());
+    }
+    if let Some(to) = to {
+        sql.push_str(" AND day <= ?");
+        params.push(to.clone().into());
+    }
+    sql
+}
+
+fn partial_aggregates(
+    conn: &Connection,
+    partial: &[(i64, i64)],
+) -> rusqlite::Result<Vec<Aggregate>> {
+    let events = Events::of(conn, source::detect(conn)?)?;
+    let idle_gap = idle_gap_secs(&AppConfig::load().unwrap_or_default());
+    partial
+        .iter()
+        .map(|(l, u)| {
+            let (lo, hi) = events.raw_range(Some(*l), Some(*u));
+            aggregate(conn, &events, lo, hi, idle_gap)
+        })
+        .collect()
+}
+
+// 时间范围内的总访问次数与各站点访问次数
+pub fn site_totals(
+    conn: &Connection,
+    lower: Option<i64>,
+    upper: Option<i64>,
+) -> rusqlite::Result<(i64, HashMap<String, i64>)> {
+    let key = source_key(conn);
+    let parts = split(lower, upper);
+    let mut total = 0;
+    let mut sites: HashMap<String, i64> = HashMap::new();
+
+    if let Some((from, to)) = &parts.full_days {
+        let mut params: Vec<rusqlite::types::Value> = vec![key.clone().into()];
+        let filter = day_filter(from, to, &mut params);
+        total += conn
+            .query_row(
+                &format!(
+                    "SELECT SUM(total_visits) FROM rollup.rollup_daily WHERE source = ?{}",
+                    filter
+                ),
+                rusqlite::params_from_iter(params.iter()),
+                |r| r.get::<_, Option<i64>>(0),
+            )?
+            .unwrap_or(0);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT site, SUM(visits) FROM rollup.rollup_daily_sites WHERE source = ?{} GROUP BY site",
+            filter
+        ))?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            *sites.entry(row.get(0)?).or_default() += row.get::<_, i64>(1)?;
+        }
+    }
+    for acc in partial_aggregates(conn, &parts.partial)? {
+        total += acc.days.values().map(|t| t.visits).sum::<i64>();
+        for ((_, site), tally) in acc.sites {
+            *sites.entry(site).or_default() += tally.visits;
+        }
+    }
+    Ok((total, sites))
+}
+
+// 时间范围内各类别的访问次数
+pub fn category_totals(
+    conn: &Connection,
+    lower: Option<i64>,
+    upper: Option<i64>,
+) -> rusqlite::Result<HashMap<String, i64>> {
+    let key = source_key(conn);
+    let parts = split(lower, upper);
+    let mut categories: HashMap<String, i64> = HashMap::new();
+
+    if let Some((from, to)) = &parts.full_days {
+        let mut params: Vec<rusqlite::types::Value> = vec![key.into()];
+        let filter = day_filter(from, to, &mut params);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT category, SUM(visits) FROM rollup.rollup_daily_categories WHERE source = ?{} GROUP BY category",
+            filter
+        ))?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            *categories.entry(row.get(0)?).or_default() += row.get::<_, i64>(1)?;
+        }
+    }
+    for acc in partial_aggregates(conn, &parts.partial)? {
+        for ((_, category), tally) in acc.categories {
+            *categories.entry(category).or_default() += tally.visits;
+        }
+    }
+    Ok(categories)
+}
+
+// [from, to] 内按 站点 × 本地日期 的访问次数，from 为 None 时从最早一天开始
+pub fn daily_site_counts(
+    conn: &Connection,
+    from: Option<NaiveDate>,
+    to: NaiveDate,
+) -> rusqlite::Result<HashMap<String, HashMap<NaiveDate, i64>>> {
+    let fmt = |d: NaiveDate| d.format("%Y-%m-%d").to_string();
+    let mut params: Vec<rusqlite::types::Value> = vec![source_key(conn).into()];
+    let filter = day_filter(&from.map(fmt), &Some(fmt(to)), &mut params);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT site, day, visits FROM rollup.rollup_daily_sites WHERE source = ?{}",
+        filter
+    ))?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    let mut counts: HashMap<String, HashMap<NaiveDate, i64>> = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let day: String = row.get(1)?;
+        let Ok(day) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") else {
+            continue;
+        };
+        *counts
+            .entry(row.get(0)?)
+            .or_default()
+            .entry(day)
+            .or_default() += row.get::<_, i64>(2)?;
+    }
+    Ok(counts)
+}
+
+// 时间范围内按本地小时（0-23）的访问次数
+pub fn hourly_totals(
+    conn: &Connection,
+    lower: Option<i64>,
+    upper: Option<i64>,
+) -> rusqlite::Result<[i64; 24]> {
+    let key = source_key(conn);
+    let parts = split(lower, upper);
+    let mut hours = [0i64; 24];
+
+    if let Some((from, to)) = &parts.full_days {
+        let mut params: Vec<rusqlite::types::Value> = vec![key.into()];
+        let filter = day_filter(from, to, &mut params);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT hour, SUM(visits) FROM rollup.rollup_daily_hours WHERE source = ?{} GROUP BY hour",
+            filter
+        ))?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+        while let Some(row) = rows.next()? {
+            if let Some(slot) = hours.get_mut(row.get::<_, usize>(0)?) {
+                *slot += row.get::<_, i64>(1)?;
+            }
+        }
+    }
+    for acc in partial_aggregates(conn, &parts.partial)? {
+        for ((_, hour), visits) in acc.hours {
+            if let Some(slot) = hours.get_mut(hour as usize) {
+                *slot += visits;
+            }
+        }
+    }
+    Ok(hours)
+}
+
+// 某一天某个站点 / 类别的访问次数与停留时长
+pub struct DayTally {
+    pub day: NaiveDate,
+    pub key: String,
+    pub tally: Tally,
+}
+
+// 时间范围内按日的 站点 / 类别 访问次数与停留时长
+pub fn daily_tallies(
+    conn: &Connection,
+    lower: Option<i64>,
+    upper: Option<i64>,
+) -> rusqlite::Result<(Vec<DayTally>, Vec<DayTally>)> {
+    let parse = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok();
+    let key = source_key(conn);
+    let parts = split(lower, upper);
+    let (mut sites, mut categories) = (Vec::new(), Vec::new());
+
+    if let Some((from, to)) = &parts.full_days {
+        for (table, column, out) in [
+            ("rollup_daily_sites", "site", &mut sites),
+            ("rollup_daily_categories", "category", &mut categories),
+        ] {
+            let mut params: Vec<rusqlite::types::Value> = vec![key.clone().into()];
+            let filter = day_filter(from, to, &mut params);
+            let mut stmt = conn.prepare(&format!(
+                "SELECT day, {}, visits, secs FROM rollup.{} WHERE source = ?{}",
+                column, table, filter
+            ))?;
+            let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+            while let Some(row) = rows.next()? {
+                let Some(day) = parse(&row.get::<_, String>(0)?) else {
+                    continue;
+                };
+                out.push(DayTally {
+                    day,
+                    key: row.get(1)?,
+                    tally: Tally {
+                        visits: row.get(2)?,
+                        secs: row.get(3)?,
+                    },
+                });
+            }
+        }
+    }
+    for acc in partial_aggregates(conn, &parts.partial)? {
+        for (map, out) in [(acc.sites, &mut sites), (acc.categories, &mut categories)] {
+            out.extend(map.into_iter().filter_map(|((day, key), tally)| {
+                Some(DayTally {
+                    day: parse(&day)?,
+                    key,
+                    tally,
+                })
+            }));
+        }
+    }
+    Ok((sites, categories))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::categories::CategoryRules;
+    use crate::exclusion::{self, Blocklist};
+
+    const IDLE_GAP: i64 = 1_800;
+    const URLS: [&str; 4] = [
+        "https://a.example.com/x",
+        "https://b.example.org/y",
+        "https://github.com/z",
+        "https://news.ycombinator.com/item",
+    ];
+
+    fn base_day() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+    }
+
+    // 内存中的 Chromium 格式数据库，汇总表附加为内存库
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT);
+            CREATE TABLE visits (id INTEGER PRIMARY KEY, url INTEGER, visit_time INTEGER,
+                transition INTEGER, visit_duration INTEGER DEFAULT 0);",
+        )
+        .unwrap();
+        for (i, url) in URLS.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO urls (id, url, title) VALUES (?, ?, ?)",
+                params![i as i64 + 1, url, format!("page {}", i)],
+            )
+            .unwrap();
+        }
+        crate::host::register(&conn).unwrap();
+        exclusion::register(&conn, &Blocklist::default()).unwrap();
+        categories::register(&conn, &CategoryRules::default()).unwrap();
+        attach(&conn).unwrap();
+        conn
+    }
+
+    // 从 first_day 起 days 天，每天在若干个非整点时刻访问各个 URL
+    fn add_visits(conn: &Connection, first_day: i64, days: i64) {
+        for d in first_day..first_day + days {
+            let midnight = local_midnight(base_day() + Duration::days(d));
+            for (i, secs) in [0, 59, 3_601, 7 * 3_600 + 17, 13 * 3_600, 86_399]
+                .into_iter()
+                .enumerate()
+            {
+                let url = (d as usize + i) % URLS.len() + 1;
+                let time = source::unix_to_chromium_time(midnight + secs) + 123_456;
+                // 链接跳转（transition 0）与子框架（3）交替出现，后者不计入
+                let transition = if i == 4 { 3 } else { 0 };
+                conn.execute(
+                    "INSERT INTO visits (url, visit_time, transition) VALUES (?, ?, ?)",
+                    params![url as i64, time, transition],
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    // 直接逐行统计 Unix 秒闭区间内的访问，作为对照
+    fn row_level(
+        conn: &Connection,
+        lower: Option<i64>,
+        upper: Option<i64>,
+    ) -> (i64, HashMap<String, i64>, HashMap<String, i64>) {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT url_host(u.url), url_category(u.url, u.title), COUNT(*)
+                FROM visits v JOIN urls u ON u.id = v.url
+                WHERE v.transition NOT IN (3, 4) AND NOT excluded(u.url, u.title)
+                AND v.visit_time / 1000000 - {offset} >= ?1
+                AND v.visit_time / 1000000 - {offset} <= ?2
+                GROUP BY 1, 2",
+                offset = WEBKIT_EPOCH_OFFSET_SECS
+            ))
+            .unwrap();
+        let mut rows = stmt
+            .query(params![
+                lower.unwrap_or(i64::MIN),
+                upper.unwrap_or(i64::MAX)
+            ])
+            .unwrap();
+        let (mut total, mut sites, mut cats) = (0, HashMap::new(), HashMap::new());
+        while let Some(row) = rows.next().unwrap() {
+            let visits: i64 = row.get(2).unwrap();
+            total += visits;
+            *sites.entry(row.get(0).unwrap()).or_default() += visits;
+            let category: Option<String> = row.get(1).unwrap();
+            *cats
+                .entry(category.unwrap_or_else(|| UNCATEGORIZED.to_string()))
+                .or_default() += visits;
+        }
+        (total, sites, cats)
+    }
+
+    fn assert_matches(conn: &Connection, lower: Option<i64>, upper: Option<i64>) {
+        let (total, sites, cats) = row_level(conn, lower, upper);
+        assert_eq!(
+            site_totals(conn, lower, upper).unwrap(),
+            (total, sites),
+            "{:?}..{:?}",
+            lower,
+            upper
+        );
+        assert_eq!(
+            category_totals(conn, lower, upper).unwrap(),
+            cats,
+            "{:?}..{:?}",
+            lower,
+            upper
+        );
+    }
+
+    fn covered_rows(conn: &Connection) -> i64 {
+        conn.query_row(
+            "SELECT covered_rows FROM rollup.rollup_sources WHERE source = ?",
+            [source_key(conn)],
+            |r| r.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn appended_visits_are_added_incrementally() {
+        let conn = open();
+        add_visits(&conn, 0, 3);
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+        assert_matches(&conn, None, None);
+        assert_eq!(covered_rows(&conn), 18);
+
+        add_visits(&conn, 3, 2);
+        // 新增访问追加到已有的某一天
+        let time = source::unix_to_chromium_time(local_midnight(base_day()) + 600);
+        conn.execute(
+            "INSERT INTO visits (url, visit_time, transition) VALUES (1, ?, 0)",
+            [time + 10 * 86_400_000_000],
+        )
+        .unwrap();
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+        assert_eq!(covered_rows(&conn), 31);
+        assert_matches(&conn, None, None);
+        let day = local_midnight(base_day() + Duration::days(10));
+        assert_matches(&conn, Some(day), Some(day + 86_399));
+    }
+
+    #[test]
+    fn deleted_or_expired_rows_trigger_rebuild() {
+        let conn = open();
+        add_visits(&conn, 0, 5);
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+
+        // 浏览器过期清理最早的两天，并删除中间的一条访问
+        let cutoff = source::unix_to_chromium_time(local_midnight(base_day() + Duration::days(2)));
+        conn.execute("DELETE FROM visits WHERE visit_time < ?", [cutoff])
+            .unwrap();
+        conn.execute(
+            "DELETE FROM visits WHERE id = (SELECT MIN(id) FROM visits WHERE transition = 0)",
+            [],
+        )
+        .unwrap();
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+        assert_matches(&conn, None, None);
+        let daily: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM rollup.rollup_daily WHERE day < ?",
+                [(base_day() + Duration::days(2))
+                    .format("%Y-%m-%d")
+                    .to_string()],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(daily, 0);
+    }
+
+    #[test]
+    fn rules_hash_change_triggers_rebuild() {
+        let conn = open();
+        add_visits(&conn, 0, 4);
+        refresh_with_rules(&conn, "before", IDLE_GAP).unwrap();
+
+        let blocklist = Blocklist {
+            domains: vec!["github.com".into()],
+            ..Default::default()
+        };
+        exclusion::register(&conn, &blocklist).unwrap();
+        refresh_with_rules(&conn, "after", IDLE_GAP).unwrap();
+        assert_matches(&conn, None, None);
+        let (_, sites) = site_totals(&conn, None, None).unwrap();
+        assert!(!sites.contains_key("github.com"));
+    }
+
+    #[test]
+    fn non_midnight_ranges_match_row_level() {
+        let conn = open();
+        add_visits(&conn, 0, 6);
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+
+        let day = |d: i64| local_midnight(base_day() + Duration::days(d));
+        let ranges = [
+            (Some(day(1)), Some(day(3) - 1)),
+            (Some(day(1) + 3_000), Some(day(3) + 50_000)),
+            (Some(day(1) + 59), Some(day(1) + 3_601)),
+            (Some(day(2) - 1), Some(day(2))),
+            (Some(day(2) + 1), Some(day(4) - 2)),
+            (Some(day(0) + 86_399), Some(day(5) + 86_399)),
+            (None, Some(day(3) + 7 * 3_600 + 16)),
+            (Some(day(4) + 7 * 3_600 + 18), None),
+            (Some(day(3) + 10), Some(day(3) + 5)),
+        ];
+        for (lower, upper) in ranges {
+            assert_matches(&conn, lower, upper);
+        }
+    }
+
+    // 逐行估算停留时长，作为对照：浏览器时长优先，否则取到下一次访问的间隔，以空闲阈值封顶
+    fn row_level_dwell(conn: &Connection) -> (HashMap<String, f64>, [i64; 24]) {
+        let mut stmt = conn
+            .prepare(
+                "SELECT url_host(u.url), v.visit_time, v.visit_duration
+                FROM visits v JOIN urls u ON u.id = v.url
+                WHERE v.transition NOT IN (3, 4) ORDER BY v.visit_time",
+            )
+            .unwrap();
+        let rows: Vec<(String, i64, i64)> = stmt
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (mut secs, mut hours) = (HashMap::new(), [0i64; 24]);
+        for (i, (site, time, duration)) in rows.iter().enumerate() {
+            let unix = source::chromium_time_to_unix(*time);
+            let dwell = if *duration > 0 {
+                (*duration as f64 / 1_000_000.0).min(IDLE_GAP as f64)
+            } else {
+                match rows.get(i + 1) {
+                    Some((_, next, _))
+                        if source::chromium_time_to_unix(*next) - unix <= IDLE_GAP =>
+                    {
+                        (source::chromium_time_to_unix(*next) - unix) as f64
+                    }
+                    _ => DEFAULT_DWELL_SECS,
+                }
+            };
+            *secs.entry(site.clone()).or_default() += dwell;
+            let hour = chrono::TimeZone::timestamp_opt(&chrono::Local, unix, 0)
+                .unwrap()
+                .format("%H")
+                .to_string();
+            hours[hour.parse::<usize>().unwrap()] += 1;
+        }
+        (secs, hours)
+    }
+
+    fn rollup_dwell(conn: &Connection) -> HashMap<String, f64> {
+        let (sites, _) = daily_tallies(conn, None, None).unwrap();
+        let mut secs = HashMap::new();
+        for item in sites {
+            *secs.entry(item.key).or_default() += item.tally.secs;
+        }
+        secs
+    }
+
+    #[test]
+    fn dwell_and_hours_match_row_level_across_increments() {
+        let conn = open();
+        add_visits(&conn, 0, 3);
+        conn.execute(
+            "UPDATE visits SET visit_duration = 90000000 WHERE id = 2",
+            [],
+        )
+        .unwrap();
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+        let (secs, hours) = row_level_dwell(&conn);
+        assert_eq!(rollup_dwell(&conn), secs);
+        assert_eq!(hourly_totals(&conn, None, None).unwrap(), hours);
+
+        // 追加访问后，原来最后一次访问的停留时长改为到新访问的间隔
+        let last: i64 = conn
+            .query_row("SELECT MAX(visit_time) FROM visits", [], |r| r.get(0))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO visits (url, visit_time, transition) VALUES (1, ?, 0)",
+            [last + 120_000_000],
+        )
+        .unwrap();
+        add_visits(&conn, 3, 1);
+        refresh_with_rules(&conn, "rules", IDLE_GAP).unwrap();
+        let (secs, hours) = row_level_dwell(&conn);
+        assert_eq!(rollup_dwell(&conn), secs);
+        assert_eq!(hourly_totals(&conn, None, None).unwrap(), hours);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::domain::SearchQueryItem;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// 搜索引擎识别规则：host 支持后缀匹配（bing.com 匹配 cn.bing.com），
+// 以 ".*" 结尾表示任意顶级域（google.* 匹配 www.google.co.jp）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchEngineRule {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    pub params: Vec<String>,
+}
+
+fn rule(name: &str, host: &str, path_prefix: Option<&str>, params: &[&str]) -> SearchEngineRule {
+    SearchEngineRule {
+        name: name.to_string(),
+        host: host.to_string(),
+        path_prefix: path_prefix.map(|p| p.to_string()),
+        params: params.iter().map(|p| p.to_string()).collect(),
+    }
+}
+
+pub fn builtin_rules() -> Vec<SearchEngineRule> {
+    vec![
+        rule("Google", "google.*", Some("/search"), &["q"]),
+        rule("Bing", "bing.com", Some("/search"), &["q"]),
+        rule("DuckDuckGo", "duckduckgo.com", None, &["q"]),
+        rule("Baidu", "baidu.com", Some("/s"), &["wd", "word"]),
+        rule("GitHub", "github.com", Some("/search"), &["q"]),
+        rule(
+            "Stack Overflow",
+            "stackoverflow.com",
+            Some("/search"),
+            &["q"],
+        ),
+    ]
+}
+
+// 用户规则优先于内置规则
+pub fn effective_rules(user_rules: &[SearchEngineRule]) -> Vec<SearchEngineRule> {
+    user_rules.iter().cloned().chain(builtin_rules()).collect()
+}
+
+// 国家顶级域下常见的二级域（co.jp、com.au 等）
+const SECOND_LEVEL_LABELS: &[&str] = &["co", "com", "net", "org", "ac", "gov", "edu", "ne", "or"];
+
+// 是否为顶级域后缀：单级 TLD，或 “常见二级域 + 两字母国家域”
+fn is_public_suffix(labels: &[&str]) -> bool {
+    let is_tld = |l: &str| l.len() >= 2 && l.chars().all(|c| c.is_ascii_alphabetic());
+    match labels {
+        [tld] => is_tld(tld),
+        [sld, cc] => SECOND_LEVEL_LABELS.contains(sld) && cc.len() == 2 && is_tld(cc),
+        _ => false,
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    if let Some(base) = pattern.strip_suffix(".*") {
+        let labels: Vec<&str> = host.split('.').collect();
+        let base_labels: Vec<&str> = base.split('.').collect();
+        // base 必须紧挨在顶级域后缀之前，google.evil.com 之类不算
+        return (1..=2).any(|suffix_len| {
+            labels.len() >= base_labels.len() + suffix_len && {
+                let split = labels.len() - suffix_len;
+                labels[split - base_labels.len()..split] == base_labels[..]
+                    && is_public_suffix(&labels[split..])
+            }
+        });
+    }
+    host == pattern || host.ends_with(&format!(".{}", pattern))
+}
+
+fn path_matches(prefix: &Option<String>, path: &str) -> bool {
+    match prefix.as_deref() {
+        None | Some("") | Some("/") => true,
+        Some(prefix) => {
+            let prefix = prefix.trim_end_matches('/');
+            path == prefix || path.starts_with(&format!("{}/", prefix))
+        }
+    }
+}
+
+// 从 URL 中提取 (搜索引擎名, 搜索词)
+pub fn extract(url: &str, rules: &[SearchEngineRule]) -> Option<(String, String)> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    let rule = rules
+        .iter()
+        .find(|r| host_matches(&r.host, &host) && path_matches(&r.path_prefix, parsed.path()))?;
+    let query = parsed
+        .query_pairs()
+        .find(|(k, v)| rule.params.iter().any(|p| p == k) && !v.trim().is_empty())
+        .map(|(_, v)| v.split_whitespace().collect::<Vec<_>>().join(" "))?;
+    Some((rule.name.clone(), query))
+}
+
+struct Agg {
+    engine: String,
+    query: String,
+    count: i64,
+    last_searched_time: i64,
+    result_pages: i64,
+}
+
+// 汇总时间范围内的搜索词，按次数降序；engine 为空时包含全部搜索引擎
+pub fn collect_queries(
+    conn: &Connection,
+    rules: &[SearchEngineRule],
+    lower: Option<i64>,
+    upper: Option<i64>,
+    engine: Option<&str>,
+) -> rusqlite::Result<Vec<SearchQueryItem>> {
+    let mut sql = format!(
+        "SELECT url, num_visits, last_visited_time FROM navigation_history
+        WHERE url LIKE '%?%' AND {}",
+        crate::exclusion::condition("url", "title")
+    );
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(lower) = lower {
+        sql.push_str(" AND last_visited_time >= ?");
+        params.push(lower.into());
+    }
+    if let Some(upper) = upper {
+        sql.push_str(" AND last_visited_time <= ?");
+        params.push(upper.into());
+    }
+
+    // 以 (引擎, 小写搜索词) 分组，展示最近一次的原始写法
+    let mut groups: HashMap<(String, String), Agg> = HashMap::new();
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    while let Some(row) = rows.next()? {
+        let url: String = row.get(0)?;
+        let (name, query) = match extract(&url, rules) {
+            Some(found) => found,
+            None => continue,
+        };
+        if engine.is_some_and(|e| !e.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+        let num_visits: i64 = row.get::<_, Option<i64>>(1)?.unwrap_or(0);
+        let last_visited_time: i64 = row.get::<_, Option<i64>>(2)?.unwrap_or(0);
+        let agg = groups
+            .entry((name.clone(), query.to_lowercase()))
+            .or_insert_with(|| Agg {
+                engine: name,
+                query: query.clone(),
+                count: 0,
+                last_searched_time: 0,
+                result_pages: 0,
+            });
+        agg.count += num_visits.max(1);
+        agg.result_pages += 1;
+        if last_visited_time > agg.last_searched_time {
+            agg.last_searched_time = last_visited_time;
+            agg.query = query;
+        }
+    }
+
+    let mut items: Vec<SearchQueryItem> = groups
+        .into_values()
+        .map(|a| SearchQueryItem {
+            engine: a.engine,
+            query: a.query,
+            count: a.count,
+            last_searched_time: a.last_searched_time,
+            result_pages: a.result_pages,
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.last_searched_time.cmp(&a.last_searched_time))
+    });
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_tld_matches_only_public_suffixes() {
+        for host in [
+            "google.com",
+            "www.google.com",
+            "google.co.uk",
+            "www.google.co.jp",
+        ] {
+            assert!(host_matches("Google.*", host), "{}", host);
+        }
+        for host in [
+            "google.evil.com",
+            "google.com.evil.com",
+            "notgoogle.com",
+            "google",
+            "google.c0m",
+        ] {
+            assert!(!host_matches("google.*", host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn plain_host_matches_subdomains() {
+        assert!(host_matches("bing.com", "bing.com"));
+        assert!(host_matches("bing.com", "cn.bing.com"));
+        assert!(!host_matches("bing.com", "notbing.com"));
+        assert!(!host_matches("bing.com", "bing.com.cn"));
+    }
+
+    #[test]
+    fn extract_uses_host_path_and_params() {
+        let rules = builtin_rules();
+        assert_eq!(
+            extract("https://www.google.co.uk/search?q=rust++lifetimes", &rules),
+            Some(("Google".into(), "rust lifetimes".into()))
+        );
+        assert_eq!(extract("https://google.evil.com/search?q=x", &rules), None);
+        assert_eq!(extract("https://www.google.com/maps?q=x", &rules), None);
+        assert_eq!(
+            extract("https://www.baidu.com/s?ie=utf-8&wd=%20&word=天气", &rules),
+            Some(("Baidu".into(), "天气".into()))
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::domain::SearchTermItem;
+use crate::source;
+use rusqlite::Connection;
+
+// Chromium keyword_search_terms：地址栏搜索词，关联 urls 中的结果页，
+// 按 normalized_term 合并，重复次数取结果页的访问次数
+pub fn list_search_terms(
+    conn: &Connection,
+    keyword: Option<&str>,
+    lower: Option<i64>,
+    upper: Option<i64>,
+    order_by_recent: bool,
+    limit: i64,
+    offset: i64,
+) -> rusqlite::Result<(Vec<SearchTermItem>, i64)> {
+    let mut where_clauses: Vec<String> = vec![crate::exclusion::condition("u.url", "u.title")];
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(lower) = lower {
+        where_clauses.push("v.visit_time >= ?".to_string());
+        params.push(source::unix_to_chromium_time(lower).into());
+    }
+    if let Some(upper) = upper {
+        where_clauses.push("v.visit_time <= ?".to_string());
+        params.push(source::unix_to_chromium_time(upper).into());
+    }
+    if let Some(kw) = keyword.filter(|k| !k.is_empty()) {
+        where_clauses.push("(k.normalized_term LIKE ? OR k.term LIKE ?)".to_string());
+        params.push(format!("%{}%", kw.to_lowercase()).into());
+        params.push(format!("%{}%", kw).into());
+    }
+    let where_sql = if where_clauses.is_empty() {
+        "".to_string()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+    let from_sql = format!(
+        "FROM keyword_search_terms k
+        JOIN urls u ON u.id = k.url_id
+        JOIN visits v ON v.url = u.id
+        {}",
+        where_sql
+    );
+
+    let order_sql = if order_by_recent {
+        "ORDER BY last_time DESC"
+    } else {
+        "ORDER BY searches DESC, last_time DESC"
+    };
+    // 只有一个 MAX() 聚合时，SQLite 的裸列取自最大值所在行，即最近一次访问的结果页
+    let items_sql = format!(
+        "SELECT k.normalized_term, k.term, COUNT(v.id) AS searches, COUNT(DISTINCT u.id),
+            MAX(v.visit_time) AS last_time, u.url, u.title
+        {}
+        GROUP BY k.normalized_term
+        {}
+        LIMIT ? OFFSET ?",
+        from_sql, order_sql
+    );
+    let count_sql = format!(
+        "SELECT COUNT(*) FROM (SELECT k.normalized_term {} GROUP BY k.normalized_term)",
+        from_sql
+    );
+
+    let total: i64 =
+        conn.query_row(&count_sql, rusqlite::params_from_iter(params.iter()), |r| {
+            r.get(0)
+        })?;
+
+    let mut all_params = params.clone();
+    all_params.push(limit.into());
+    all_params.push(offset.into());
+    let mut stmt = conn.prepare(&items_sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(all_params.iter()))?;
+    let mut items = Vec::new();
+    while let Some(row) = rows.next()? {
+        items.push(SearchTermItem {
+            normalized_term: row.get(0)?,
+            term: row.get(1)?,
+            searches: row.get(2)?,
+            result_pages: row.get(3)?,
+            last_searched_time: source::chromium_time_to_unix(row.get(4)?),
+            result_url: row.get(5)?,
+            result_title: row.get(6)?,
+        });
+    }
+    Ok((items, total))
+}
@@ -0,0 +1,241 @@
+use crate::api::{self, HistoryFilters};
+use crate::config::AppConfig;
+use crate::domain::{ApiServerStatus, AppError, AppResult};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// 仅监听回环地址的 HTTP/JSON 接口，供编辑器插件、启动器、脚本等本地工具查询历史
+// 每次启动生成随机令牌写入应用目录，请求需携带 Authorization: Bearer <令牌>
+pub const DEFAULT_PORT: u16 = 47821;
+const TOKEN_FILE: &str = "api_token";
+
+struct Running {
+    server: Arc<Server>,
+    port: u16,
+    worker: JoinHandle<()>,
+}
+
+static RUNNING: Lazy<Mutex<Option<Running>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn token_path() -> AppResult<PathBuf> {
+    Ok(AppConfig::get_app_dir()
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .join(TOKEN_FILE))
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// 令牌文件仅当前用户可读
+fn write_token(token: &str) -> AppResult<PathBuf> {
+    let path = token_path()?;
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| AppError::Internal(format!("写入令牌文件失败: {}", e)))?;
+    file.write_all(token.as_bytes())
+        .map_err(|e| AppError::Internal(format!("写入令牌文件失败: {}", e)))?;
+    Ok(path)
+}
+
+pub fn start(port: u16) -> AppResult<ApiServerStatus> {
+    let mut running = RUNNING.lock().unwrap();
+    if running.as_ref().is_some_and(|r| r.port == port) {
+        drop(running);
+        return Ok(status());
+    }
+    if let Some(previous) = running.take() {
+        shutdown(previous);
+    }
+
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| AppError::Internal(format!("启动 HTTP 服务失败: {}", e)))?;
+    let token = generate_token();
+    write_token(&token)?;
+
+    let server = Arc::new(server);
+    let listener = Arc::clone(&server);
+    let worker = std::thread::spawn(move || {
+        for request in listener.incoming_requests() {
+            let response = handle(&request, port, &token);
+            let _ = request.respond(response);
+        }
+    });
+    *running = Some(Running {
+        server,
+        port,
+        worker,
+    });
+    drop(running);
+
+    Ok(status())
+}
+
+pub fn stop() {
+    if let Some(running) = RUNNING.lock().unwrap().take() {
+        shutdown(running);
+    }
+}
+
+fn shutdown(running: Running) {
+    running.server.unblock();
+    let _ = running.worker.join();
+    if let Ok(path) = token_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+pub fn status() -> ApiServerStatus {
+    let config = AppConfig::load().unwrap_or_default();
+    let port = RUNNING.lock().unwrap().as_ref().map(|r| r.port);
+    ApiServerStatus {
+        enabled: config.api_server_enabled,
+        running: port.is_some(),
+        port: port.unwrap_or(config.api_server_port),
+        token_path: port
+            .and_then(|_| token_path().ok())
+            .map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+// 应用启动时按配置自动开启
+pub fn start_if_enabled() {
+    if let Ok(config) = AppConfig::load() {
+        if config.api_server_enabled {
+            if let Err(e) = start(config.api_server_port) {
+                eprintln!("HTTP 服务启动失败: {}", e);
+            }
+        }
+    }
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+// 逐字节比较全部内容，避免按提前返回的耗时猜测令牌
+fn token_matches(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn handle(request: &Request, port: u16, token: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    if !request.remote_addr().is_some_and(|a| a.ip().is_loopback()) {
+        return error_response(403, "仅允许本机访问");
+    }
+    // Host 校验防止 DNS 重绑定，带 Origin 的请求一律拒绝（不返回任何 CORS 头）
+    let host_ok = header(request, "Host")
+        .is_some_and(|h| h == format!("127.0.0.1:{}", port) || h == format!("localhost:{}", port));
+    if !host_ok || header(request, "Origin").is_some() {
+        return error_response(403, "不允许跨域请求");
+    }
+    if *request.method() != Method::Get {
+        return error_response(405, "仅支持 GET 请求");
+    }
+    let authorized = header(request, "Authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|t| token_matches(t.trim(), token));
+    if !authorized {
+        return error_response(401, "令牌无效")
+            .with_header(Header::from_bytes(&b"WWW-Authenticate"[..], &b"Bearer"[..]).unwrap());
+    }
+
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let result = match path {
+        "/api/history" => list_history(&params),
+        "/api/stats" => {
+            api::stats_overview(params.get("time_range").cloned()).and_then(|s| to_json(&s))
+        }
+        "/api/search" => search(&params),
+        _ => return error_response(404, "接口不存在"),
+    };
+
+    match result {
+        Ok(body) => json_response(200, body),
+        Err(e @ AppError::Invalid(_)) => error_response(400, &e.to_string()),
+        Err(e) => error_response(500, &e.to_string()),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> AppResult<String> {
+    serde_json::to_string(value).map_err(|e| AppError::Internal(e.to_string()))
+}
+
+fn number_param(params: &HashMap<String, String>, key: &str, default: u32) -> AppResult<u32> {
+    params.get(key).map_or(Ok(default), |v| {
+        v.parse()
+            .map_err(|_| AppError::Invalid(format!("{} 参数无效", key)))
+    })
+}
+
+fn list_history(params: &HashMap<String, String>) -> AppResult<String> {
+    let page = number_param(params, "page", 1)?;
+    let page_size = number_param(params, "page_size", 50)?;
+    let filters = HistoryFilters {
+        keyword: params.get("keyword").cloned(),
+        time_range: params.get("time_range").cloned(),
+        locale: params.get("locale").cloned(),
+        sort_by: params.get("sort_by").cloned(),
+        sort_order: params.get("sort_order").cloned(),
+        transition: params.get("transition").cloned(),
+        collapse_duplicates: params
+            .get("collapse_duplicates")
+            .map(|v| v == "true" || v == "1"),
+    };
+    to_json(&api::list_history(page, page_size, filters)?)
+}
+
+fn search(params: &HashMap<String, String>) -> AppResult<String> {
+    let q = params
+        .get("q")
+        .cloned()
+        .ok_or_else(|| AppError::Invalid("缺少 q 参数".into()))?;
+    let limit = number_param(params, "limit", 10)?;
+    to_json(&api::search_suggest(q, Some(limit))?)
+}
+
+fn json_response(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(
+            Header::from_bytes(
+                &b"Content-Type"[..],
+                &b"application/json; charset=utf-8"[..],
+            )
+            .unwrap(),
+        )
+        .with_header(Header::from_bytes(&b"Cache-Control"[..], &b"no-store"[..]).unwrap())
+        .with_header(Header::from_bytes(&b"X-Content-Type-Options"[..], &b"nosniff"[..]).unwrap())
+}
+
+fn error_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, serde_json::json!({ "error": message }).to_string())
+}
@@ -0,0 +1,124 @@
+use crate::domain::{BrowsingSession, SiteCount, VisitRecord};
+use crate::host::host_of;
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_GAP_MINUTES: u32 = 30;
+const DOMINANT_SITES: usize = 3;
+
+fn finish(visits: Vec<VisitRecord>) -> BrowsingSession {
+    let start_time = visits.first().map(|v| v.visit_time).unwrap_or(0);
+    let end_time = visits.last().map(|v| v.visit_time).unwrap_or(start_time);
+
+    let mut site_counts: HashMap<&str, i64> = HashMap::new();
+    let mut pages: HashSet<&str> = HashSet::new();
+    for v in &visits {
+        pages.insert(v.url.as_str());
+        if let Some(site) = host_of(&v.url) {
+            *site_counts.entry(site).or_insert(0) += 1;
+        }
+    }
+    let mut dominant_sites: Vec<SiteCount> = site_counts
+        .into_iter()
+        .map(|(site, visits)| SiteCount {
+            site: site.to_string(),
+            visits,
+        })
+        .collect();
+    dominant_sites.sort_by(|a, b| b.visits.cmp(&a.visits).then_with(|| a.site.cmp(&b.site)));
+    dominant_sites.truncate(DOMINANT_SITES);
+
+    BrowsingSession {
+        start_time,
+        end_time,
+        duration_secs: end_time - start_time,
+        page_count: visits.len() as i64,
+        distinct_pages: pages.len() as i64,
+        dominant_sites,
+        visits,
+    }
+}
+
+// 按空闲间隔切分会话：相邻两次访问相隔超过 gap_secs 即开始新会话。
+// visits 需按时间升序排列。
+pub fn sessionize(visits: Vec<VisitRecord>, gap_secs: i64) -> Vec<BrowsingSession> {
+    let mut sessions = Vec::new();
+    let mut current: Vec<VisitRecord> = Vec::new();
+
+    for visit in visits {
+        if let Some(last) = current.last() {
+            if visit.visit_time - last.visit_time > gap_secs {
+                sessions.push(finish(std::mem::take(&mut current)));
+            }
+        }
+        current.push(visit);
+    }
+    if !current.is_empty() {
+        sessions.push(finish(current));
+    }
+    sessions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visit(visit_id: i64, url: &str, visit_time: i64) -> VisitRecord {
+        VisitRecord {
+            visit_id,
+            url: url.to_string(),
+            title: None,
+            visit_time,
+            from_visit: None,
+            transition: None,
+            transition_type: None,
+            duration_secs: None,
+        }
+    }
+
+    #[test]
+    fn splits_on_gaps_longer_than_threshold() {
+        let visits = vec![
+            visit(1, "https://a.com/1", 0),
+            visit(2, "https://a.com/2", 60),
+            // 恰好等于间隔不切分
+            visit(3, "https://b.com/", 120),
+            visit(4, "https://c.com/", 121 + 60),
+        ];
+        let sessions = sessionize(visits, 60);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(
+            (
+                sessions[0].start_time,
+                sessions[0].end_time,
+                sessions[0].duration_secs
+            ),
+            (0, 120, 120)
+        );
+        assert_eq!(sessions[0].page_count, 3);
+        assert_eq!(sessions[1].page_count, 1);
+        assert_eq!(sessions[1].duration_secs, 0);
+        assert!(sessionize(Vec::new(), 60).is_empty());
+    }
+
+    #[test]
+    fn summarises_pages_and_dominant_sites() {
+        let visits = vec![
+            visit(1, "https://d.com/", 0),
+            visit(2, "https://b.com/x", 1),
+            visit(3, "https://b.com/x", 2),
+            visit(4, "https://a.com/", 3),
+            visit(5, "https://c.com/", 4),
+            visit(6, "https://b.com/y", 5),
+        ];
+        let session = sessionize(visits, 60).remove(0);
+        assert_eq!(session.page_count, 6);
+        assert_eq!(session.distinct_pages, 5);
+        // 按访问次数降序，同次数按站点名排序，最多三个
+        let sites: Vec<(&str, i64)> = session
+            .dominant_sites
+            .iter()
+            .map(|s| (s.site.as_str(), s.visits))
+            .collect();
+        assert_eq!(sites, vec![("b.com", 3), ("a.com", 1), ("c.com", 1)]);
+    }
+}
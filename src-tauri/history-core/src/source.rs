@@ -0,0 +1,95 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+// Chromium 时间戳为 1601-01-01 起的微秒数
+pub(crate) const WEBKIT_EPOCH_OFFSET_SECS: i64 = 11_644_473_600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    // 应用自有格式（navigation_history 表）
+    NavigationHistory,
+    // Chrome / Edge 等 Chromium 内核浏览器的 History 文件
+    Chromium,
+    // Firefox 的 places.sqlite
+    Firefox,
+}
+
+impl SourceKind {
+    // 是否包含逐次访问记录（visits / moz_historyvisits）
+    pub fn has_visits(self) -> bool {
+        matches!(self, SourceKind::Chromium | SourceKind::Firefox)
+    }
+}
+
+pub fn table_exists(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![name],
+        |r| r.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn detect(conn: &Connection) -> rusqlite::Result<SourceKind> {
+    if table_exists(conn, "navigation_history")? {
+        return Ok(SourceKind::NavigationHistory);
+    }
+    if table_exists(conn, "urls")? && table_exists(conn, "visits")? {
+        return Ok(SourceKind::Chromium);
+    }
+    if table_exists(conn, "moz_places")? && table_exists(conn, "moz_historyvisits")? {
+        return Ok(SourceKind::Firefox);
+    }
+    // 无法识别时按应用自有格式处理，由后续查询报告缺表错误
+    Ok(SourceKind::NavigationHistory)
+}
+
+pub fn chromium_time_to_unix(micros: i64) -> i64 {
+    if micros <= 0 {
+        return 0;
+    }
+    micros / 1_000_000 - WEBKIT_EPOCH_OFFSET_SECS
+}
+
+pub fn unix_to_chromium_time(secs: i64) -> i64 {
+    (secs + WEBKIT_EPOCH_OFFSET_SECS) * 1_000_000
+}
+
+pub fn firefox_time_to_unix(micros: i64) -> i64 {
+    if micros <= 0 {
+        return 0;
+    }
+    micros / 1_000_000
+}
+
+// 为浏览器原生数据库创建临时兼容视图，使现有基于 navigation_history 的查询可以直接复用。
+// 临时视图只存在于当前连接中，不会写入数据库文件。
+pub fn install_compat_views(conn: &Connection, kind: SourceKind) -> rusqlite::Result<()> {
+    let view_sql = match kind {
+        SourceKind::NavigationHistory => return Ok(()),
+        SourceKind::Chromium => format!(
+            r#"
+            CREATE TEMP VIEW IF NOT EXISTS navigation_history AS
+            SELECT url, id, title, NULL AS metadata,
+                CASE WHEN last_visit_time > 0 THEN last_visit_time / 1000000 - {offset} ELSE 0 END AS last_visited_time,
+                visit_count AS num_visits, NULL AS product_entity_id, NULL AS locale,
+                NULL AS titledata, NULL AS urldata, NULL AS page_profile
+            FROM urls
+            WHERE hidden = 0;
+            "#,
+            offset = WEBKIT_EPOCH_OFFSET_SECS
+        ),
+        SourceKind::Firefox => r#"
+            CREATE TEMP VIEW IF NOT EXISTS navigation_history AS
+            SELECT url, id, title, NULL AS metadata,
+                COALESCE(last_visit_date, 0) / 1000000 AS last_visited_time,
+                visit_count AS num_visits, NULL AS product_entity_id, NULL AS locale,
+                NULL AS titledata, NULL AS urldata, NULL AS page_profile
+            FROM moz_places
+            WHERE hidden = 0 AND visit_count > 0;
+            "#
+        .to_string(),
+    };
+    conn.execute_batch(&view_sql)
+}
@@ -0,0 +1,241 @@
+use crate::detail::parse_keywords;
+use crate::domain::{Suggestion, SuggestionKind};
+use crate::frecency;
+use crate::host::host_of;
+use crate::source;
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::RwLock;
+
+// 连接打开时构建的内存前缀索引，随连接切换而重建
+static INDEX: Lazy<RwLock<Option<SuggestIndex>>> = Lazy::new(|| RwLock::new(None));
+
+struct Entry {
+    kind: SuggestionKind,
+    text: String,
+    url: Option<String>,
+    num_visits: i64,
+    last_visited_time: i64,
+}
+
+pub struct SuggestIndex {
+    entries: Vec<Entry>,
+    // (小写词条, 条目下标, 是否为完整文本)，按词条排序以便二分查找前缀
+    keys: Vec<(Box<str>, u32, bool)>,
+}
+
+struct Ranked {
+    score: f64,
+    idx: u32,
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score
+    }
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// 反向比较，使 BinaryHeap 成为最小堆，保留得分最高的 limit 个
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.score.total_cmp(&self.score)
+    }
+}
+
+pub(crate) fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+}
+
+impl SuggestIndex {
+    fn push(&mut self, entry: Entry, extra_keys: &[&str]) {
+        let idx = self.entries.len() as u32;
+        let lower = entry.text.to_lowercase();
+        let mut keys: Vec<String> = tokens(&lower)
+            .map(|t| t.to_string())
+            .chain(extra_keys.iter().map(|k| k.to_lowercase()))
+            .filter(|k| *k != lower)
+            .collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            self.keys.push((key.into_boxed_str(), idx, false));
+        }
+        self.keys.push((lower.into_boxed_str(), idx, true));
+        self.entries.push(entry);
+    }
+
+    pub fn build(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut index = SuggestIndex {
+            entries: Vec::new(),
+            keys: Vec::new(),
+        };
+        let mut hosts: HashMap<String, (i64, i64)> = HashMap::new();
+
+        let mut stmt = conn.prepare(
+            "SELECT url, title, num_visits, last_visited_time FROM navigation_history
+            WHERE NOT excluded(url, title)",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let url: String = row.get(0)?;
+            let title: Option<String> = row.get(1)?;
+            let num_visits: i64 = row.get::<_, Option<i64>>(2)?.unwrap_or(0);
+            let last_visited_time: i64 = row.get::<_, Option<i64>>(3)?.unwrap_or(0);
+
+            if let Some(host) = host_of(&url) {
+                let agg = hosts.entry(host.to_string()).or_insert((0, 0));
+                agg.0 += num_visits;
+                agg.1 = agg.1.max(last_visited_time);
+            }
+            if let Some(title) = title.filter(|t| !t.trim().is_empty()) {
+                index.push(
+                    Entry {
+                        kind: SuggestionKind::Title,
+                        text: title,
+                        url: Some(url),
+                        num_visits,
+                        last_visited_time,
+                    },
+                    &[],
+                );
+            }
+        }
+
+        for (host, (num_visits, last_visited_time)) in hosts {
+            let bare = host.strip_prefix("www.").unwrap_or(&host).to_string();
+            index.push(
+                Entry {
+                    kind: SuggestionKind::Host,
+                    text: host,
+                    url: None,
+                    num_visits,
+                    last_visited_time,
+                },
+                &[&bare],
+            );
+        }
+
+        if source::table_exists(conn, "product_entities")? {
+            let mut stmt = conn.prepare(
+                "SELECT pe.search_keywords, SUM(n.num_visits), MAX(n.last_visited_time)
+                FROM product_entities pe
+                JOIN navigation_history n ON n.product_entity_id = pe.product_entity_id
+                WHERE NOT excluded(n.url, n.title)
+                GROUP BY pe.product_entity_id",
+            )?;
+            let mut keyword_stats: HashMap<String, (i64, i64)> = HashMap::new();
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let num_visits: i64 = row.get::<_, Option<i64>>(1)?.unwrap_or(0);
+                let last_visited_time: i64 = row.get::<_, Option<i64>>(2)?.unwrap_or(0);
+                for keyword in parse_keywords(row.get(0)?) {
+                    let agg = keyword_stats.entry(keyword).or_insert((0, 0));
+                    agg.0 += num_visits;
+                    agg.1 = agg.1.max(last_visited_time);
+                }
+            }
+            for (keyword, (num_visits, last_visited_time)) in keyword_stats {
+                index.push(
+                    Entry {
+                        kind: SuggestionKind::Keyword,
+                        text: keyword,
+                        url: None,
+                        num_visits,
+                        last_visited_time,
+                    },
+                    &[],
+                );
+            }
+        }
+
+        index.keys.sort_unstable();
+        Ok(index)
+    }
+
+    pub fn query(&self, q: &str, limit: usize, now: i64, half_life_days: f64) -> Vec<Suggestion> {
+        let q = q.trim().to_lowercase();
+        if q.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let start = self
+            .keys
+            .partition_point(|(k, _, _)| k.as_ref() < q.as_str());
+        // 0 = 未命中, 1 = 词条前缀命中, 2 = 完整文本前缀命中
+        let mut hits = vec![0u8; self.entries.len()];
+        let mut matched: Vec<u32> = Vec::new();
+        for (_, idx, whole) in self.keys[start..]
+            .iter()
+            .take_while(|(k, _, _)| k.starts_with(q.as_str()))
+        {
+            let hit = &mut hits[*idx as usize];
+            if *hit == 0 {
+                matched.push(*idx);
+            }
+            *hit = (*hit).max(if *whole { 2 } else { 1 });
+        }
+
+        let mut heap: BinaryHeap<Ranked> = BinaryHeap::with_capacity(limit + 1);
+        for idx in matched {
+            let entry = &self.entries[idx as usize];
+            let mut score = frecency::score(
+                entry.num_visits,
+                entry.last_visited_time,
+                now,
+                half_life_days,
+            );
+            // 整体前缀命中优先于词内命中
+            if hits[idx as usize] == 2 {
+                score *= 2.0;
+            }
+            heap.push(Ranked { score, idx });
+            if heap.len() > limit {
+                heap.pop();
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|r| {
+                let entry = &self.entries[r.idx as usize];
+                Suggestion {
+                    kind: entry.kind,
+                    text: entry.text.clone(),
+                    url: entry.url.clone(),
+                    score: r.score,
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn rebuild(conn: &Connection) {
+    let built = match SuggestIndex::build(conn) {
+        Ok(index) => Some(index),
+        Err(e) => {
+            eprintln!("构建搜索建议索引失败: {}", e);
+            None
+        }
+    };
+    *INDEX.write().unwrap() = built;
+}
+
+pub fn query(q: &str, limit: usize, half_life_days: f64) -> Option<Vec<Suggestion>> {
+    let now = chrono::Utc::now().timestamp();
+    INDEX
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|index| index.query(q, limit, now, half_life_days))
+}
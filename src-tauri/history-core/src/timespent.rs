@@ -0,0 +1,143 @@
+use crate::domain::{CategoryTime, SiteTime, TimeBudgetPeriod, TimeBudgetReport};
+use crate::localtime::local_midnight;
+use crate::rollup::DayTally;
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::HashMap;
+
+pub const UNCATEGORIZED: &str = "未分类";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Week,
+}
+
+impl Granularity {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        match value {
+            Some("day") | None => Some(Granularity::Day),
+            Some("week") => Some(Granularity::Week),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+        }
+    }
+
+    // 返回 (周期标签, 周期起点的本地零点时间戳)
+    pub fn bucket(self, date: NaiveDate) -> (String, i64) {
+        match self {
+            Granularity::Day => (date.format("%Y-%m-%d").to_string(), local_midnight(date)),
+            Granularity::Week => {
+                let iso = date.iso_week();
+                let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                (
+                    format!("{}-W{:02}", iso.year(), iso.week()),
+                    local_midnight(monday),
+                )
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Acc {
+    secs: f64,
+    visits: i64,
+}
+
+fn top_sites(map: HashMap<String, Acc>, limit: usize) -> Vec<SiteTime> {
+    let mut items: Vec<SiteTime> = map
+        .into_iter()
+        .map(|(site, a)| SiteTime {
+            site,
+            secs: a.secs,
+            visits: a.visits,
+        })
+        .collect();
+    items.sort_by(|a, b| b.secs.total_cmp(&a.secs).then_with(|| a.site.cmp(&b.site)));
+    items.truncate(limit);
+    items
+}
+
+fn top_categories(map: HashMap<String, Acc>, limit: usize) -> Vec<CategoryTime> {
+    let mut items: Vec<CategoryTime> = map
+        .into_iter()
+        .map(|(category, a)| CategoryTime {
+            category,
+            secs: a.secs,
+            visits: a.visits,
+        })
+        .collect();
+    items.sort_by(|a, b| {
+        b.secs
+            .total_cmp(&a.secs)
+            .then_with(|| a.category.cmp(&b.category))
+    });
+    items.truncate(limit);
+    items
+}
+
+// 停留时长在汇总表中按日预先估算（见 rollup::aggregate），这里按周期合并并取前 top_n
+pub fn build_report(
+    sites: &[DayTally],
+    categories: &[DayTally],
+    granularity: Granularity,
+    top_n: usize,
+) -> TimeBudgetReport {
+    // 周期 -> (起点, 站点累计, 类别累计)
+    type PeriodAcc = (i64, HashMap<String, Acc>, HashMap<String, Acc>);
+    let mut periods: HashMap<String, PeriodAcc> = HashMap::new();
+    let mut all_sites: HashMap<String, Acc> = HashMap::new();
+    let mut all_categories: HashMap<String, Acc> = HashMap::new();
+
+    for (item, is_site) in sites
+        .iter()
+        .map(|s| (s, true))
+        .chain(categories.iter().map(|c| (c, false)))
+    {
+        let (label, start) = granularity.bucket(item.day);
+        let entry = periods
+            .entry(label)
+            .or_insert_with(|| (start, HashMap::new(), HashMap::new()));
+        let (period, all) = if is_site {
+            (&mut entry.1, &mut all_sites)
+        } else {
+            (&mut entry.2, &mut all_categories)
+        };
+        for acc in [
+            period.entry(item.key.clone()).or_default(),
+            all.entry(item.key.clone()).or_default(),
+        ] {
+            acc.secs += item.tally.secs;
+            acc.visits += item.tally.visits;
+        }
+    }
+
+    // 每次访问都归入某个类别（含未分类），总时长按类别合计
+    let mut periods: Vec<TimeBudgetPeriod> = periods
+        .into_iter()
+        .map(
+            |(period, (period_start, sites, categories))| TimeBudgetPeriod {
+                period,
+                period_start,
+                total_secs: categories.values().map(|a| a.secs).sum(),
+                sites: top_sites(sites, top_n),
+                categories: top_categories(categories, top_n),
+            },
+        )
+        .collect();
+    periods.sort_by_key(|p| p.period_start);
+
+    TimeBudgetReport {
+        granularity: granularity.as_str().to_string(),
+        total_secs: all_categories.values().map(|a| a.secs).sum(),
+        periods,
+        top_sites: top_sites(all_sites, top_n),
+        top_categories: top_categories(all_categories, top_n),
+    }
+}
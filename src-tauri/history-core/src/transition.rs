@@ -0,0 +1,315 @@
+use crate::domain::{SiteTransitions, TransitionCount, TransitionStats, VisitRecord};
+use crate::host::host_of;
+use crate::source::SourceKind;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Chromium transition 高位限定符：客户端 / 服务端重定向
+const CHROMIUM_REDIRECT_MASK: i64 = 0xC000_0000;
+const CHROMIUM_CORE_MASK: i64 = 0xFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transition {
+    Link,
+    Typed,
+    Bookmark,
+    Subframe,
+    Generated,
+    FormSubmit,
+    Reload,
+    Keyword,
+    Redirect,
+    Download,
+    Other,
+}
+
+impl Transition {
+    pub const ALL: [Transition; 11] = [
+        Transition::Link,
+        Transition::Typed,
+        Transition::Bookmark,
+        Transition::Subframe,
+        Transition::Generated,
+        Transition::FormSubmit,
+        Transition::Reload,
+        Transition::Keyword,
+        Transition::Redirect,
+        Transition::Download,
+        Transition::Other,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Transition::Link => "link",
+            Transition::Typed => "typed",
+            Transition::Bookmark => "bookmark",
+            Transition::Subframe => "subframe",
+            Transition::Generated => "generated",
+            Transition::FormSubmit => "form_submit",
+            Transition::Reload => "reload",
+            Transition::Keyword => "keyword",
+            Transition::Redirect => "redirect",
+            Transition::Download => "download",
+            Transition::Other => "other",
+        }
+    }
+
+    // Chromium 低 8 位为核心类型，重定向由高位限定符标记
+    pub fn from_chromium(raw: i64) -> Self {
+        if raw & CHROMIUM_REDIRECT_MASK != 0 {
+            return Transition::Redirect;
+        }
+        match raw & CHROMIUM_CORE_MASK {
+            0 => Transition::Link,
+            1 => Transition::Typed,
+            2 => Transition::Bookmark,
+            3 | 4 => Transition::Subframe,
+            5 => Transition::Generated,
+            7 => Transition::FormSubmit,
+            8 => Transition::Reload,
+            9 | 10 => Transition::Keyword,
+            _ => Transition::Other,
+        }
+    }
+
+    // Firefox moz_historyvisits.visit_type
+    pub fn from_firefox(raw: i64) -> Self {
+        match raw {
+            1 => Transition::Link,
+            2 => Transition::Typed,
+            3 => Transition::Bookmark,
+            4 | 8 => Transition::Subframe,
+            5 | 6 => Transition::Redirect,
+            7 => Transition::Download,
+            9 => Transition::Reload,
+            _ => Transition::Other,
+        }
+    }
+
+    pub fn decode(kind: SourceKind, raw: Option<i64>) -> Option<Self> {
+        let raw = raw?;
+        match kind {
+            SourceKind::NavigationHistory => None,
+            SourceKind::Chromium => Some(Self::from_chromium(raw)),
+            SourceKind::Firefox => Some(Self::from_firefox(raw)),
+        }
+    }
+
+    fn chromium_codes(self) -> &'static [i64] {
+        match self {
+            Transition::Link => &[0],
+            Transition::Typed => &[1],
+            Transition::Bookmark => &[2],
+            Transition::Subframe => &[3, 4],
+            Transition::Generated => &[5],
+            Transition::FormSubmit => &[7],
+            Transition::Reload => &[8],
+            Transition::Keyword => &[9, 10],
+            Transition::Redirect | Transition::Download | Transition::Other => &[],
+        }
+    }
+
+    fn firefox_codes(self) -> &'static [i64] {
+        match self {
+            Transition::Link => &[1],
+            Transition::Typed => &[2],
+            Transition::Bookmark => &[3],
+            Transition::Subframe => &[4, 8],
+            Transition::Redirect => &[5, 6],
+            Transition::Download => &[7],
+            Transition::Reload => &[9],
+            Transition::Generated
+            | Transition::FormSubmit
+            | Transition::Keyword
+            | Transition::Other => &[],
+        }
+    }
+
+    // 生成匹配该类型的 SQL 条件，column 为原始 transition / visit_type 列
+    pub fn sql_condition(self, kind: SourceKind, column: &str) -> Option<String> {
+        let list = |codes: &[i64]| {
+            codes
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let cond = match kind {
+            SourceKind::NavigationHistory => return None,
+            SourceKind::Chromium => {
+                let core = format!("({} & {})", column, CHROMIUM_CORE_MASK);
+                let core_cond = match self {
+                    Transition::Redirect => {
+                        return Some(format!("({} & {}) != 0", column, CHROMIUM_REDIRECT_MASK))
+                    }
+                    Transition::Download => "0".to_string(),
+                    Transition::Other => {
+                        format!("{} NOT IN (0, 1, 2, 3, 4, 5, 7, 8, 9, 10)", core)
+                    }
+                    _ => format!("{} IN ({})", core, list(self.chromium_codes())),
+                };
+                format!(
+                    "({} & {}) = 0 AND {}",
+                    column, CHROMIUM_REDIRECT_MASK, core_cond
+                )
+            }
+            SourceKind::Firefox => match self {
+                Transition::Other => format!("{} NOT BETWEEN 1 AND 9", column),
+                _ if self.firefox_codes().is_empty() => "0".to_string(),
+                _ => format!("{} IN ({})", column, list(self.firefox_codes())),
+            },
+        };
+        Some(format!("({})", cond))
+    }
+}
+
+fn to_counts(map: HashMap<Transition, i64>) -> Vec<TransitionCount> {
+    let mut counts: Vec<TransitionCount> = map
+        .into_iter()
+        .map(|(transition, visits)| TransitionCount { transition, visits })
+        .collect();
+    counts.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then_with(|| a.transition.as_str().cmp(b.transition.as_str()))
+    });
+    counts
+}
+
+// 统计各跳转类型的访问占比（整体 + 访问量前 top_n 个站点）
+pub fn build_stats(visits: &[VisitRecord], top_n: usize) -> TransitionStats {
+    let mut totals: HashMap<Transition, i64> = HashMap::new();
+    let mut per_site: HashMap<&str, HashMap<Transition, i64>> = HashMap::new();
+
+    for v in visits {
+        let transition = v.transition_type.unwrap_or(Transition::Other);
+        *totals.entry(transition).or_insert(0) += 1;
+        if let Some(site) = host_of(&v.url) {
+            *per_site
+                .entry(site)
+                .or_default()
+                .entry(transition)
+                .or_insert(0) += 1;
+        }
+    }
+
+    let mut sites: Vec<SiteTransitions> = per_site
+        .into_iter()
+        .map(|(site, counts)| SiteTransitions {
+            site: site.to_string(),
+            total: counts.values().sum(),
+            counts: to_counts(counts),
+        })
+        .collect();
+    sites.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.site.cmp(&b.site)));
+    sites.truncate(top_n);
+
+    TransitionStats {
+        total: totals.values().sum(),
+        totals: to_counts(totals),
+        sites,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn decodes_chromium_and_firefox_codes() {
+        use Transition::*;
+        // 链起止限定符 0x10000000 / 0x20000000 不影响核心类型
+        for (raw, expected) in [
+            (0, Link),
+            (0x3000_0001, Typed),
+            (0x1000_0008, Reload),
+            (10, Keyword),
+            (0x4000_0000, Redirect),
+            (0x8000_0001, Redirect),
+            (6, Other),
+        ] {
+            assert_eq!(
+                Transition::decode(SourceKind::Chromium, Some(raw)),
+                Some(expected),
+                "{:#x}",
+                raw
+            );
+        }
+        for (raw, expected) in [
+            (1, Link),
+            (6, Redirect),
+            (7, Download),
+            (9, Reload),
+            (0, Other),
+        ] {
+            assert_eq!(
+                Transition::decode(SourceKind::Firefox, Some(raw)),
+                Some(expected)
+            );
+        }
+        assert_eq!(
+            Transition::decode(SourceKind::NavigationHistory, Some(1)),
+            None
+        );
+        assert_eq!(Transition::decode(SourceKind::Chromium, None), None);
+    }
+
+    #[test]
+    fn sql_condition_agrees_with_decode() {
+        let conn = Connection::open_in_memory().unwrap();
+        let samples: [(SourceKind, &[i64]); 2] = [
+            (
+                SourceKind::Chromium,
+                &[
+                    0,
+                    1,
+                    2,
+                    3,
+                    4,
+                    5,
+                    6,
+                    7,
+                    8,
+                    9,
+                    10,
+                    11,
+                    0x3000_0001,
+                    0x4000_0000,
+                    0x8000_0005,
+                ],
+            ),
+            (SourceKind::Firefox, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10]),
+        ];
+        for (kind, codes) in samples {
+            for t in Transition::ALL {
+                let cond = t.sql_condition(kind, "v").unwrap();
+                for &raw in codes {
+                    let matched: bool = conn
+                        .query_row(
+                            &format!("SELECT {} FROM (SELECT ?1 AS v)", cond),
+                            [raw],
+                            |r| r.get(0),
+                        )
+                        .unwrap();
+                    assert_eq!(
+                        matched,
+                        Transition::decode(kind, Some(raw)) == Some(t),
+                        "{:?} {:?} {:#x}",
+                        kind,
+                        t,
+                        raw
+                    );
+                }
+            }
+        }
+        assert!(Transition::Link
+            .sql_condition(SourceKind::NavigationHistory, "t")
+            .is_none());
+    }
+}
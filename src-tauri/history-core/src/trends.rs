@@ -0,0 +1,136 @@
+use crate::domain::{SiteTrend, TrendPoint};
+use chrono::{Duration, NaiveDate};
+use std::collections::HashMap;
+
+// 按站点的每日访问序列（来自按日汇总表）：7 / 28 天滑动平均，以及相对此前 28 天的 z-score 异常标记
+pub const SHORT_WINDOW_DAYS: usize = 7;
+pub const LONG_WINDOW_DAYS: usize = 28;
+pub const DEFAULT_Z_THRESHOLD: f64 = 3.0;
+// 此前可用天数不足时不计算 z-score
+const MIN_BASELINE_DAYS: usize = 7;
+// 访问次数过少的日子不标记为异常，避免低频站点的噪声
+const MIN_ANOMALY_VISITS: i64 = 3;
+
+fn mean_std(values: &[i64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<i64>() as f64 / n;
+    let var = values
+        .iter()
+        .map(|v| (*v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    (mean, var.sqrt())
+}
+
+// 从 series_start 起逐日补零（此前的天只作为滑动窗口的基线），输出 [start, end] 内的点
+pub fn build_series(
+    site: &str,
+    days: &HashMap<NaiveDate, i64>,
+    series_start: NaiveDate,
+    start: NaiveDate,
+    end: NaiveDate,
+    threshold: f64,
+) -> SiteTrend {
+    let total_days = (end - series_start).num_days().max(0) as usize + 1;
+    let values: Vec<i64> = (0..total_days)
+        .map(|i| {
+            let day = series_start + Duration::days(i as i64);
+            days.get(&day).copied().unwrap_or(0)
+        })
+        .collect();
+    let avg = |i: usize, window: usize| {
+        let from = (i + 1).saturating_sub(window);
+        let slice = &values[from..=i];
+        slice.iter().sum::<i64>() as f64 / slice.len() as f64
+    };
+
+    let first = (start - series_start).num_days().max(0) as usize;
+    let mut points = Vec::new();
+    for (i, &visits) in values.iter().enumerate().skip(first) {
+        let baseline = &values[i.saturating_sub(LONG_WINDOW_DAYS)..i];
+        // 此前 28 天完全没有波动时 z-score 无意义
+        let z_score = if baseline.len() >= MIN_BASELINE_DAYS {
+            let (mean, std) = mean_std(baseline);
+            (std > 0.0).then(|| (visits as f64 - mean) / std)
+        } else {
+            None
+        };
+        points.push(TrendPoint {
+            day: (series_start + Duration::days(i as i64))
+                .format("%Y-%m-%d")
+                .to_string(),
+            visits,
+            avg_7d: avg(i, SHORT_WINDOW_DAYS),
+            avg_28d: avg(i, LONG_WINDOW_DAYS),
+            z_score,
+            anomaly: visits >= MIN_ANOMALY_VISITS && z_score.is_some_and(|z| z >= threshold),
+        });
+    }
+
+    SiteTrend {
+        site: site.to_string(),
+        total_visits: points.iter().map(|p| p.visits).sum(),
+        anomaly_days: points.iter().filter(|p| p.anomaly).count() as u32,
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(offset: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap() + Duration::days(offset)
+    }
+
+    #[test]
+    fn flags_spikes_against_the_previous_28_days() {
+        // 基线 1、3 交替：均值 2，标准差 1
+        let mut days: HashMap<NaiveDate, i64> = (0..28)
+            .map(|i| (date(i), if i % 2 == 0 { 1 } else { 3 }))
+            .collect();
+        days.insert(date(28), 10);
+        let trend = build_series("a.com", &days, date(0), date(28), date(29), 3.0);
+
+        assert_eq!(trend.points.len(), 2);
+        assert_eq!((trend.total_visits, trend.anomaly_days), (10, 1));
+        let spike = &trend.points[0];
+        assert_eq!(spike.day, "2024-03-29");
+        assert_eq!(spike.z_score, Some(8.0));
+        assert!(spike.anomaly);
+        assert!((spike.avg_7d - 22.0 / 7.0).abs() < 1e-9);
+        assert!((spike.avg_28d - 65.0 / 28.0).abs() < 1e-9);
+        // 缺失的日子补零
+        let next = &trend.points[1];
+        assert_eq!(
+            (next.day.as_str(), next.visits, next.anomaly),
+            ("2024-03-30", 0, false)
+        );
+    }
+
+    #[test]
+    fn needs_baseline_variance_and_minimum_visits() {
+        // 基线不足 7 天
+        let days: HashMap<NaiveDate, i64> = [(date(0), 1), (date(3), 50)].into_iter().collect();
+        let trend = build_series("a.com", &days, date(0), date(0), date(3), 3.0);
+        assert!(trend
+            .points
+            .iter()
+            .all(|p| p.z_score.is_none() && !p.anomaly));
+        assert_eq!(trend.points[0].avg_7d, 1.0);
+
+        // 基线无波动
+        let mut flat: HashMap<NaiveDate, i64> = (0..10).map(|i| (date(i), 4)).collect();
+        flat.insert(date(10), 40);
+        let trend = build_series("a.com", &flat, date(0), date(10), date(10), 3.0);
+        assert_eq!(trend.points[0].z_score, None);
+
+        // z-score 足够高但访问次数过少
+        let mut sparse: HashMap<NaiveDate, i64> = (0..10).map(|i| (date(i), i % 2)).collect();
+        sparse.insert(date(10), 2);
+        sparse.insert(date(9), 0);
+        let point = &build_series("a.com", &sparse, date(0), date(10), date(10), 0.5).points[0];
+        assert!(point.z_score.is_some_and(|z| z >= 0.5));
+        assert!(!point.anomaly);
+    }
+}
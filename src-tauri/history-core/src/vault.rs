@@ -0,0 +1,422 @@
+use crate::config::AppConfig;
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use once_cell::sync::Lazy;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zeroize::Zeroizing;
+
+// 加密文件格式：MAGIC | 版本 | Argon2 参数(m, t, p) | salt | nonce | 密文(含认证标签)
+// 头部（含 Argon2 参数、salt、nonce）整体作为附加认证数据，篡改任何字段都会导致解密失败；
+// 参数在认证之前就会使用，因此另有上限检查
+const MAGIC: &[u8; 8] = b"BHBVAULT";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN;
+pub const ENCRYPTED_SUFFIX: &str = ".enc";
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+// Argon2id：64 MiB 内存、3 轮、单线程
+const DEFAULT_KDF: KdfParams = KdfParams {
+    m_cost: 64 * 1024,
+    t_cost: 3,
+    p_cost: 1,
+};
+
+// 头部在认证前就要用于派生密钥，参数超过默认值 4 倍的文件直接拒绝，
+// 避免伪造文件触发超大内存或超长时间的 Argon2 计算
+const MAX_KDF: KdfParams = KdfParams {
+    m_cost: DEFAULT_KDF.m_cost * 4,
+    t_cost: DEFAULT_KDF.t_cost * 4,
+    p_cost: DEFAULT_KDF.p_cost * 4,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KdfParams {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+#[derive(Clone)]
+pub struct VaultKey {
+    key: Zeroizing<[u8; 32]>,
+    salt: [u8; SALT_LEN],
+    kdf: KdfParams,
+}
+
+impl VaultKey {
+    // 使用新的随机 salt 派生密钥
+    pub fn derive(passphrase: &str) -> Result<Self> {
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            return Err(anyhow!("密码长度至少为 {} 个字符", MIN_PASSPHRASE_LEN));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive_with(passphrase, salt, DEFAULT_KDF)
+    }
+
+    fn derive_with(passphrase: &str, salt: [u8; SALT_LEN], kdf: KdfParams) -> Result<Self> {
+        let params = Params::new(kdf.m_cost, kdf.t_cost, kdf.p_cost, Some(32))
+            .map_err(|e| anyhow!("密钥派生参数无效: {}", e))?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow!("密钥派生失败: {}", e))?;
+        Ok(Self { key, salt, kdf })
+    }
+}
+
+struct Header {
+    kdf: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+}
+
+fn parse_header(data: &[u8]) -> Result<Header> {
+    if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+        return Err(anyhow!("不是有效的加密数据库文件"));
+    }
+    if data[MAGIC.len()] != FORMAT_VERSION {
+        return Err(anyhow!("不支持的加密文件版本: {}", data[MAGIC.len()]));
+    }
+    let mut pos = MAGIC.len() + 1;
+    let mut next_u32 = || {
+        let v = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+        pos += 4;
+        v
+    };
+    let kdf = KdfParams {
+        m_cost: next_u32(),
+        t_cost: next_u32(),
+        p_cost: next_u32(),
+    };
+    if kdf.m_cost > MAX_KDF.m_cost || kdf.t_cost > MAX_KDF.t_cost || kdf.p_cost > MAX_KDF.p_cost {
+        return Err(anyhow!("加密文件的密钥派生参数超出允许范围"));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    salt.copy_from_slice(&data[pos..pos + SALT_LEN]);
+    pos += SALT_LEN;
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&data[pos..pos + NONCE_LEN]);
+    Ok(Header { kdf, salt, nonce })
+}
+
+pub fn encrypt(key: &VaultKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    for v in [key.kdf.m_cost, key.kdf.t_cost, key.kdf.p_cost] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out.extend_from_slice(&key.salt);
+    out.extend_from_slice(&nonce);
+
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &out[..HEADER_LEN],
+            },
+        )
+        .map_err(|_| anyhow!("加密失败"))?;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+fn decrypt_with(key: &VaultKey, header: &Header, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(&header.nonce),
+            Payload {
+                msg: &data[HEADER_LEN..],
+                aad: &data[..HEADER_LEN],
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("密码错误或文件已损坏"))
+}
+
+// 用密码解密，返回派生出的密钥以便后续重新加密
+pub fn decrypt(passphrase: &str, data: &[u8]) -> Result<(VaultKey, Zeroizing<Vec<u8>>)> {
+    let header = parse_header(data)?;
+    let key = VaultKey::derive_with(passphrase, header.salt, header.kdf)?;
+    let plaintext = decrypt_with(&key, &header, data)?;
+    Ok((key, plaintext))
+}
+
+// 用已解锁的密钥解密，仅当文件使用相同的 salt 与参数时可用
+pub fn decrypt_with_key(key: &VaultKey, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let header = parse_header(data)?;
+    if header.salt != key.salt || header.kdf != key.kdf {
+        return Err(anyhow!("该文件使用了不同的密码"));
+    }
+    decrypt_with(key, &header, data)
+}
+
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut magic = [0u8; MAGIC.len()];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .map(|_| &magic == MAGIC)
+        .unwrap_or(false)
+}
+
+// 先写临时文件再替换，避免写入中断导致数据库损坏
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("enc.tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// 同时替换多个加密文件：先全部写入临时文件，再逐个把原文件移到 .old 并换入新文件，
+// 任一步失败都恢复全部原文件，不会出现部分文件换了密钥的情况
+pub fn replace_all(files: &[(PathBuf, Vec<u8>)]) -> Result<()> {
+    let with_suffix = |path: &Path, suffix: &str| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    let staged: Vec<PathBuf> = files.iter().map(|(p, _)| with_suffix(p, ".tmp")).collect();
+    let written = files.iter().zip(&staged).try_for_each(|((_, data), tmp)| {
+        let mut file = fs::File::create(tmp)?;
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        for tmp in &staged {
+            let _ = fs::remove_file(tmp);
+        }
+        return Err(e.into());
+    }
+
+    let mut replaced = Vec::new();
+    for ((path, _), tmp) in files.iter().zip(&staged) {
+        let old = with_suffix(path, ".old");
+        let swapped = fs::rename(path, &old).and_then(|_| {
+            fs::rename(tmp, path).inspect_err(|_| {
+                let _ = fs::rename(&old, path);
+            })
+        });
+        if let Err(e) = swapped {
+            for (path, old) in &replaced {
+                let _ = fs::rename(old, path);
+            }
+            for tmp in &staged {
+                let _ = fs::remove_file(tmp);
+            }
+            return Err(anyhow!("替换加密文件失败 {:?}: {}", path, e));
+        }
+        replaced.push((path.clone(), old));
+    }
+    for (_, old) in replaced {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+// 以零覆盖后删除明文文件（尽力而为，SSD / 写时复制文件系统上无法保证）
+pub fn wipe_file(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let len = fs::metadata(path)?.len() as usize;
+    {
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        let zeros = vec![0u8; 64 * 1024];
+        let mut written = 0;
+        while written < len {
+            let n = zeros.len().min(len - written);
+            file.write_all(&zeros[..n])?;
+            written += n;
+        }
+        file.sync_all()?;
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+// 擦除数据库文件及其 WAL / SHM（尽力而为，失败只记录）
+pub fn wipe_db_files(path: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut name = path.as_os_str().to_owned();
+        name.push(suffix);
+        let file = Path::new(&name);
+        if let Err(e) = wipe_file(file) {
+            eprintln!("擦除明文文件失败 {:?}: {}", file, e);
+        }
+    }
+}
+
+// 用已解锁的密钥加密明文数据库文件，写入 <路径>.enc 后擦除明文，返回加密文件路径
+pub fn encrypt_file(key: &VaultKey, path: &Path) -> Result<String> {
+    let plaintext = Zeroizing::new(fs::read(path)?);
+    let target = format!("{}{}", path.to_string_lossy(), ENCRYPTED_SUFFIX);
+    write_atomic(Path::new(&target), &encrypt(key, &plaintext)?)?;
+    wipe_db_files(path);
+    Ok(target)
+}
+
+struct Session {
+    key: VaultKey,
+    last_activity: Instant,
+}
+
+// 解锁后的密钥仅保存在内存中，锁定时清零
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn is_unlocked() -> bool {
+    SESSION.lock().unwrap().is_some()
+}
+
+pub fn current_key() -> Option<VaultKey> {
+    SESSION.lock().unwrap().as_ref().map(|s| s.key.clone())
+}
+
+pub fn start_session(key: VaultKey) {
+    *SESSION.lock().unwrap() = Some(Session {
+        key,
+        last_activity: Instant::now(),
+    });
+}
+
+pub fn end_session() {
+    SESSION.lock().unwrap().take();
+}
+
+pub fn touch() {
+    if let Some(session) = SESSION.lock().unwrap().as_mut() {
+        session.last_activity = Instant::now();
+    }
+}
+
+// 将数据库映像重新加密写回当前配置的加密文件
+pub fn persist(key: &VaultKey, plaintext: &[u8]) -> Result<()> {
+    let config = AppConfig::load()?;
+    let path = config
+        .get_db_path()
+        .filter(|p| p.ends_with(ENCRYPTED_SUFFIX))
+        .ok_or_else(|| anyhow!("当前数据库不是加密文件"))?;
+    write_atomic(Path::new(&path), &encrypt(key, plaintext)?)
+}
+
+// 空闲超过配置时间后自动锁定
+pub fn spawn_auto_lock() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(Duration::from_secs(30));
+        let minutes = AppConfig::load().map(|c| c.auto_lock_minutes).unwrap_or(0);
+        if minutes == 0 {
+            continue;
+        }
+        let idle = SESSION
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|s| s.last_activity.elapsed());
+        if idle.is_some_and(|d| d >= Duration::from_secs(minutes as u64 * 60)) {
+            crate::db::lock();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试使用最小的 Argon2 参数，解密时同样从头部读取
+    fn key(passphrase: &str) -> VaultKey {
+        let kdf = KdfParams {
+            m_cost: 8,
+            t_cost: 1,
+            p_cost: 1,
+        };
+        VaultKey::derive_with(passphrase, [7; SALT_LEN], kdf).unwrap()
+    }
+
+    #[test]
+    fn round_trip_with_passphrase_and_key() {
+        let key = key("correct horse");
+        let data = encrypt(&key, b"sqlite image").unwrap();
+        let (derived, plaintext) = decrypt("correct horse", &data).unwrap();
+        assert_eq!(plaintext.as_slice(), b"sqlite image");
+        assert_eq!(
+            decrypt_with_key(&derived, &data).unwrap().as_slice(),
+            b"sqlite image"
+        );
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let data = encrypt(&key("correct horse"), b"sqlite image").unwrap();
+        assert!(decrypt("wrong horse", &data).is_err());
+        let other = VaultKey::derive_with(
+            "correct horse",
+            [8; SALT_LEN],
+            KdfParams {
+                m_cost: 8,
+                t_cost: 1,
+                p_cost: 1,
+            },
+        )
+        .unwrap();
+        assert!(decrypt_with_key(&other, &data).is_err());
+    }
+
+    #[test]
+    fn tampered_header_or_ciphertext_is_rejected() {
+        let key = key("correct horse");
+        let data = encrypt(&key, b"sqlite image").unwrap();
+        // salt 与 nonce 属于头部，作为附加认证数据参与校验
+        for pos in [
+            HEADER_LEN - 1,
+            HEADER_LEN - NONCE_LEN - 1,
+            data.len() - 1,
+            HEADER_LEN,
+        ] {
+            let mut tampered = data.clone();
+            tampered[pos] ^= 1;
+            assert!(decrypt_with(&key, &parse_header(&tampered).unwrap(), &tampered).is_err());
+        }
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        assert!(decrypt("correct horse", &bad_magic).is_err());
+        let mut huge_kdf = data.clone();
+        huge_kdf[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_header(&huge_kdf).is_err());
+    }
+
+    #[test]
+    fn replace_all_restores_originals_on_failure() {
+        let dir = std::env::temp_dir().join(format!("bhb-vault-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.db.enc");
+        fs::write(&a, b"old a").unwrap();
+        // 第二个目标不存在，换入时失败
+        let missing = dir.join("missing.db.enc");
+        let result = replace_all(&[(a.clone(), b"new a".to_vec()), (missing, b"new".to_vec())]);
+        assert!(result.is_err());
+        assert_eq!(fs::read(&a).unwrap(), b"old a");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        replace_all(&[(a.clone(), b"new a".to_vec())]).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"new a");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use crate::domain::{VisitEntry, VisitRecord};
+use crate::source::{self, SourceKind};
+use crate::transition::Transition;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+// 列出某个 URL 的全部访问记录及其来源页面（仅逐次访问型数据源）
+pub fn list_visits_for_url(
+    conn: &Connection,
+    kind: SourceKind,
+    url: &str,
+) -> rusqlite::Result<Vec<VisitEntry>> {
+    let sql = match kind {
+        SourceKind::NavigationHistory => return Ok(Vec::new()),
+        SourceKind::Chromium => {
+            "SELECT v.id, v.visit_time, v.from_visit, v.transition, v.visit_duration, ru.url
+            FROM visits v
+            JOIN urls u ON u.id = v.url
+            LEFT JOIN visits rv ON rv.id = v.from_visit
+            LEFT JOIN urls ru ON ru.id = rv.url AND NOT excluded(ru.url, ru.title)
+            WHERE u.url = ?1
+            ORDER BY v.visit_time DESC"
+        }
+        SourceKind::Firefox => {
+            "SELECT v.id, v.visit_date, v.from_visit, v.visit_type, NULL, rp.url
+            FROM moz_historyvisits v
+            JOIN moz_places p ON p.id = v.place_id
+            LEFT JOIN moz_historyvisits rv ON rv.id = v.from_visit
+            LEFT JOIN moz_places rp ON rp.id = rv.place_id AND NOT excluded(rp.url, rp.title)
+            WHERE p.url = ?1
+            ORDER BY v.visit_date DESC"
+        }
+    };
+
+    let mut stmt = conn.prepare(sql)?;
+    let mut rows = stmt.query(params![url])?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let raw_time: i64 = row.get(1)?;
+        let visit_time = match kind {
+            SourceKind::Chromium => source::chromium_time_to_unix(raw_time),
+            _ => source::firefox_time_to_unix(raw_time),
+        };
+        // Chromium 的 visit_duration 单位为微秒
+        let duration_secs = row
+            .get::<_, Option<i64>>(4)?
+            .filter(|d| *d > 0)
+            .map(|d| d as f64 / 1_000_000.0);
+        acc.push(VisitEntry {
+            visit_id: row.get(0)?,
+            visit_time,
+            from_visit: row.get::<_, Option<i64>>(2)?.filter(|v| *v > 0),
+            referrer_url: row.get(5)?,
+            transition: row.get(3)?,
+            transition_type: Transition::decode(kind, row.get(3)?),
+            duration_secs,
+        });
+    }
+    Ok(acc)
+}
+
+// 按时间升序加载时间范围内的页面访问。子框架访问不算作页面浏览，直接排除；
+// 自有格式没有逐次访问记录，以每条记录的最后访问时间近似为一次访问。
+pub fn load_visits(
+    conn: &Connection,
+    kind: SourceKind,
+    lower: Option<i64>,
+    upper: Option<i64>,
+) -> rusqlite::Result<Vec<VisitRecord>> {
+    let (base_sql, time_col, to_raw): (&str, &str, fn(i64) -> i64) = match kind {
+        SourceKind::NavigationHistory => (
+            "SELECT COALESCE(id, rowid), url, title, last_visited_time, NULL, NULL, NULL
+            FROM navigation_history WHERE last_visited_time > 0 AND NOT excluded(url, title)",
+            "last_visited_time",
+            |ts| ts,
+        ),
+        SourceKind::Chromium => (
+            "SELECT v.id, u.url, u.title, v.visit_time, v.from_visit, v.transition, v.visit_duration
+            FROM visits v JOIN urls u ON u.id = v.url
+            WHERE (v.transition & 255) NOT IN (3, 4) AND NOT excluded(u.url, u.title)",
+            "v.visit_time",
+            source::unix_to_chromium_time,
+        ),
+        SourceKind::Firefox => (
+            "SELECT v.id, p.url, p.title, v.visit_date, v.from_visit, v.visit_type, NULL
+            FROM moz_historyvisits v JOIN moz_places p ON p.id = v.place_id
+            WHERE v.visit_type NOT IN (4, 8) AND NOT excluded(p.url, p.title)",
+            "v.visit_date",
+            |ts| ts * 1_000_000,
+        ),
+    };
+
+    let mut sql = base_sql.to_string();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(lower) = lower {
+        sql.push_str(&format!(" AND {} >= ?", time_col));
+        params.push(to_raw(lower).into());
+    }
+    if let Some(upper) = upper {
+        sql.push_str(&format!(" AND {} <= ?", time_col));
+        params.push(to_raw(upper).into());
+    }
+    sql.push_str(&format!(" ORDER BY {} ASC", time_col));
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    let mut acc = Vec::new();
+    while let Some(row) = rows.next()? {
+        let raw_time: i64 = row.get(3)?;
+        let visit_time = match kind {
+            SourceKind::NavigationHistory => raw_time,
+            SourceKind::Chromium => source::chromium_time_to_unix(raw_time),
+            SourceKind::Firefox => source::firefox_time_to_unix(raw_time),
+        };
+        acc.push(VisitRecord {
+            visit_id: row.get(0)?,
+            url: row.get(1)?,
+            title: row.get(2)?,
+            visit_time,
+            from_visit: row.get::<_, Option<i64>>(4)?.filter(|v| *v > 0),
+            transition: row.get(5)?,
+            transition_type: Transition::decode(kind, row.get(5)?),
+            duration_secs: row
+                .get::<_, Option<i64>>(6)?
+                .filter(|d| *d > 0)
+                .map(|d| d as f64 / 1_000_000.0),
+        });
+    }
+    Ok(acc)
+}
+
+// 按访问 ID 批量查询对应的 URL，用于解析落在时间范围之外的来源访问
+pub fn load_visit_urls(
+    conn: &Connection,
+    kind: SourceKind,
+    ids: &[i64],
+) -> rusqlite::Result<HashMap<i64, String>> {
+    let base_sql = match kind {
+        SourceKind::NavigationHistory => return Ok(HashMap::new()),
+        SourceKind::Chromium => {
+            "SELECT v.id, u.url FROM visits v JOIN urls u ON u.id = v.url
+            WHERE NOT excluded(u.url, u.title) AND v.id IN"
+        }
+        SourceKind::Firefox => {
+            "SELECT v.id, p.url FROM moz_historyvisits v JOIN moz_places p ON p.id = v.place_id
+            WHERE NOT excluded(p.url, p.title) AND v.id IN"
+        }
+    };
+
+    let mut map = HashMap::new();
+    // 控制单条语句的参数数量
+    for chunk in ids.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(",");
+        let sql = format!("{} ({})", base_sql, placeholders);
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(chunk.iter()))?;
+        while let Some(row) = rows.next()? {
+            map.insert(row.get(0)?, row.get(1)?);
+        }
+    }
+    Ok(map)
+}
+
+// 生成“存在指定跳转类型访问的 URL”子查询，用于过滤 navigation_history
+pub fn urls_with_transition_sql(kind: SourceKind, transition: Transition) -> Option<String> {
+    let (base, column) = match kind {
+        SourceKind::NavigationHistory => return None,
+        SourceKind::Chromium => (
+            "SELECT u.url FROM visits v JOIN urls u ON u.id = v.url WHERE",
+            "v.transition",
+        ),
+        SourceKind::Firefox => (
+            "SELECT p.url FROM moz_historyvisits v JOIN moz_places p ON p.id = v.place_id WHERE",
+            "v.visit_type",
+        ),
+    };
+    let cond = transition.sql_condition(kind, column)?;
+    Some(format!("{} {}", base, cond))
+}
@@ -2,20 +2,29 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod commands;
-mod config;
-mod db;
-mod domain;
 
 use commands::{
-    browse_browser_db_file, browse_db_file, cleanup_old_dbs, copy_browser_db_to_app, get_config,
-    list_history, open_db_directory, set_browser_db_path, set_db_path, set_top_sites_count,
-    stats_overview, validate_db_path,
+    api_server_status, browse_browser_db_file, browse_db_file, category_distribution,
+    change_passphrase, cleanup_old_dbs, compare_periods, copy_browser_db_to_app, delete_history,
+    digest_report, encrypt_database, encrypt_snapshots, export_navigation_graph, generate_report,
+    get_config, get_history_detail, list_downloads, list_first_seen, list_history,
+    list_search_queries, list_search_terms, list_sessions, lock_database, lock_status,
+    navigation_graph, open_db_directory, search_suggest, set_auto_lock_minutes, set_blocklist,
+    set_browser_db_path, set_category_rules, set_db_path, set_frecency_half_life,
+    set_redaction_config, set_search_engine_rules, set_session_gap_minutes, set_top_sites_count,
+    set_url_canonical_rules, site_trends, start_api_server, stats_overview, stop_api_server,
+    time_budget, transition_stats, unlock_database, validate_db_path, wipe_snapshots,
 };
 
 fn main() {
+    history_core::vault::spawn_auto_lock();
+    history_core::server::start_if_enabled();
+
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             list_history,
+            get_history_detail,
+            search_suggest,
             stats_overview,
             get_config,
             set_db_path,
@@ -26,7 +35,40 @@ fn main() {
             set_browser_db_path,
             open_db_directory,
             cleanup_old_dbs,
-            set_top_sites_count
+            set_top_sites_count,
+            set_frecency_half_life,
+            list_sessions,
+            set_session_gap_minutes,
+            time_budget,
+            navigation_graph,
+            export_navigation_graph,
+            transition_stats,
+            list_search_queries,
+            set_search_engine_rules,
+            list_search_terms,
+            list_downloads,
+            set_url_canonical_rules,
+            set_redaction_config,
+            set_blocklist,
+            delete_history,
+            encrypt_database,
+            unlock_database,
+            lock_database,
+            change_passphrase,
+            lock_status,
+            set_auto_lock_minutes,
+            start_api_server,
+            stop_api_server,
+            api_server_status,
+            digest_report,
+            generate_report,
+            list_first_seen,
+            compare_periods,
+            set_category_rules,
+            category_distribution,
+            site_trends,
+            encrypt_snapshots,
+            wipe_snapshots
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");